@@ -1,19 +1,12 @@
 use bevy::prelude::*;
-use bevy_console::{reply, AddConsoleCommand, ConsoleCommand, ConsoleConfiguration, ConsolePlugin};
+use bevy_console::{reply, AddConsoleCommand, ConsoleCommand, ConsolePlugin};
 use clap::{Parser, ValueEnum};
 
 fn main() {
     App::new()
         // set background to red
         .add_plugins((DefaultPlugins, ConsolePlugin))
-        .insert_resource(ConsoleConfiguration {
-            arg_completions: vec![
-                vec!["custom".into(), "foo".into()],
-                vec!["custom".into(), "bar".into()],
-                vec!["custom".into(), "zoo".into()],
-            ],
-            ..Default::default()
-        })
+        // `custom foo`, `custom bar`, `custom zoo` and `custom --loud` are completed from the clap definition
         .add_console_command::<CustomCommand, _>(log_command)
         .run();
 }
@@ -31,14 +24,22 @@ pub enum Variant {
 struct CustomCommand {
     #[arg(value_enum)]
     variant: Variant,
+    /// Prints in uppercase
+    #[arg(long)]
+    loud: bool,
 }
 
 fn log_command(mut log: ConsoleCommand<CustomCommand>) {
-    if let Some(Ok(CustomCommand { variant })) = log.take() {
-        match variant {
-            Variant::Foo => reply!(log, "foo!"),
-            Variant::Bar => reply!(log, "bar!"),
-            Variant::Zoo => reply!(log, "zoo!"),
+    if let Some(Ok(CustomCommand { variant, loud })) = log.take() {
+        let msg = match variant {
+            Variant::Foo => "foo!",
+            Variant::Bar => "bar!",
+            Variant::Zoo => "zoo!",
+        };
+        if loud {
+            reply!(log, "{}", msg.to_uppercase());
+        } else {
+            reply!(log, "{}", msg);
         }
         log.ok();
     }
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use bevy_console::{
+    AddConsoleVar, ConsoleConfiguration, ConsolePlugin, ConsoleSet, ConsoleVar, ConsoleVarChanged,
+    ConsoleVars,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, ConsolePlugin))
+        // archived variables are loaded from this file at startup, `writeconfig` saves them
+        .insert_resource(ConsoleConfiguration {
+            config_file: Some("console_vars.toml".into()),
+            ..Default::default()
+        })
+        // type `fov` to print the value, `fov 100` to change it and `reset fov` to restore the default
+        .add_console_var(
+            ConsoleVar::new("fov", 90.0)
+                .description("Field of view in degrees")
+                .range(60.0, 120.0)
+                .archive(),
+        )
+        .add_console_var(ConsoleVar::new("god", false).description("Makes the player invincible"))
+        .add_systems(Update, print_changes.in_set(ConsoleSet::PostCommands))
+        .run();
+}
+
+fn print_changes(mut changes: EventReader<ConsoleVarChanged>, vars: Res<ConsoleVars>) {
+    for ConsoleVarChanged { name } in changes.read() {
+        info!("{name} changed to {:?}", vars.get_string(name));
+    }
+}
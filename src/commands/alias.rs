@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::parser::is_valid_name;
+use crate::{reply, reply_failed, ConsoleCommand, ConsoleConfiguration, ConsoleVars};
+
+/// User defined aliases, expanded before commands are executed
+#[derive(Resource, Default)]
+pub(crate) struct ConsoleAliases(pub(crate) BTreeMap<String, String>);
+
+/// Defines an alias for one or more commands, lists aliases when no command is given
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "alias")]
+pub(crate) struct AliasCommand {
+    /// Name of the alias
+    name: Option<String>,
+    /// Commands to run, for example "god; noclip"
+    command: Option<String>,
+}
+
+/// Removes an alias
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "unalias")]
+pub(crate) struct UnaliasCommand {
+    /// Name of the alias
+    name: String,
+}
+
+pub(crate) fn alias_command(
+    mut alias: ConsoleCommand<AliasCommand>,
+    config: Res<ConsoleConfiguration>,
+    vars: Res<ConsoleVars>,
+    mut aliases: ResMut<ConsoleAliases>,
+) {
+    match alias.take() {
+        Some(Ok(AliasCommand {
+            name: Some(name),
+            command: Some(command),
+        })) => {
+            if config.commands.contains_key(name.as_str()) {
+                reply_failed!(alias, "'{}' is a command and can't be aliased", name);
+            } else if vars.contains(&name) {
+                reply_failed!(alias, "'{}' is a variable and can't be aliased", name);
+            } else if !is_valid_name(&name) {
+                reply_failed!(alias, "Invalid alias name '{}'", name);
+            } else {
+                aliases.0.insert(name, command);
+                alias.ok();
+            }
+        }
+        Some(Ok(AliasCommand {
+            name: Some(name),
+            command: None,
+        })) => match aliases.0.get(&name) {
+            Some(command) => reply!(alias, "{} = \"{}\"", name, command),
+            None => reply_failed!(alias, "Alias '{}' does not exist", name),
+        },
+        Some(Ok(AliasCommand { name: None, .. })) => {
+            for (name, command) in &aliases.0 {
+                reply!(alias, "{} = \"{}\"", name, command);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn unalias_command(
+    mut unalias: ConsoleCommand<UnaliasCommand>,
+    mut aliases: ResMut<ConsoleAliases>,
+) {
+    if let Some(Ok(UnaliasCommand { name })) = unalias.take() {
+        if aliases.0.remove(&name).is_some() {
+            unalias.ok();
+        } else {
+            reply_failed!(unalias, "Alias '{}' does not exist", name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{test_app, Marks};
+    use crate::RunConsoleLine;
+
+    #[test]
+    fn test_alias_names() {
+        let mut app = test_app();
+        let output = app
+            .run_console_line("alias ab 'mark a; mark b' && ab")
+            .unwrap();
+        assert_eq!(output.result, Ok(None));
+        assert_eq!(app.world().resource::<Marks>().0, vec!["a", "b"]);
+
+        // names which would be split up or expanded before the alias is looked up
+        for name in ["a;b", "x|y", "$v", "#c"] {
+            let output = app
+                .run_console_line(&format!("alias '{name}' 'mark c'"))
+                .unwrap();
+            assert_eq!(
+                output.lines,
+                vec![format!("Invalid alias name '{name}'"), "[failed]".into()]
+            );
+        }
+        let output = app.run_console_line("alias help 'mark c'").unwrap();
+        assert!(output.result.is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::parser::split_chain;
+use crate::{
+    reply, reply_failed, CommandQueue, CommandSource, ConsoleCommand, ConsoleConfiguration,
+    ConsoleOpen,
+};
+
+/// Console lines bound to keys, run when the key is pressed while the console is closed
+#[derive(Resource, Default)]
+pub(crate) struct ConsoleBindings(pub(crate) BTreeMap<KeyCode, String>);
+
+/// Binds a key to one or more commands, shows the binding when no command is given
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "bind")]
+pub(crate) struct BindCommand {
+    /// Key, for example F1, G or Digit1
+    key: String,
+    /// Commands to run, for example "god; noclip"
+    command: Option<String>,
+}
+
+/// Removes the binding of a key
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "unbind")]
+pub(crate) struct UnbindCommand {
+    /// Key, for example F1, G or Digit1
+    key: String,
+}
+
+/// Lists the key bindings
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "bindlist")]
+pub(crate) struct BindListCommand;
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        /// Keys which can be bound, by name
+        const KEYS: &[(&str, KeyCode)] = &[$((stringify!($key), KeyCode::$key),)*];
+    };
+}
+
+key_names!(
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    Space,
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Minus,
+    Equal,
+    BracketLeft,
+    BracketRight,
+    Backslash,
+    Semicolon,
+    Quote,
+    Comma,
+    Period,
+    Slash,
+    Backquote,
+    ShiftLeft,
+    ShiftRight,
+    ControlLeft,
+    ControlRight,
+    AltLeft,
+    AltRight,
+    CapsLock,
+    Pause,
+    PrintScreen,
+    ScrollLock,
+);
+
+/// Parses a key name, e.g. `F1`, `g` for `KeyG` or `1` for `Digit1`, ignoring case
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let matches = |prefix: &str, key: &str| {
+        key.len() == prefix.len() + name.len()
+            && key.starts_with(prefix)
+            && key[prefix.len()..].eq_ignore_ascii_case(name)
+    };
+    KEYS.iter()
+        .find(|(key, _)| matches("", key) || matches("Key", key) || matches("Digit", key))
+        .map(|&(_, key)| key)
+}
+
+fn key_name(key: KeyCode) -> String {
+    format!("{key:?}")
+}
+
+pub(crate) fn bind_command(
+    mut bind: ConsoleCommand<BindCommand>,
+    config: Res<ConsoleConfiguration>,
+    mut bindings: ResMut<ConsoleBindings>,
+) {
+    let Some(Ok(BindCommand { key, command })) = bind.take() else {
+        return;
+    };
+    let Some(key) = parse_key(&key) else {
+        reply_failed!(bind, "Unknown key '{}'", key);
+        return;
+    };
+
+    match command {
+        Some(_) if config.keys.contains(&key) => {
+            reply_failed!(
+                bind,
+                "'{}' opens the console and can't be bound",
+                key_name(key)
+            );
+        }
+        Some(command) => match split_chain(&command) {
+            Ok(_) => {
+                bindings.0.insert(key, command);
+                bind.ok();
+            }
+            Err(err) => reply_failed!(bind, "{}", err),
+        },
+        None => match bindings.0.get(&key) {
+            Some(command) => reply!(bind, "{} = \"{}\"", key_name(key), command),
+            None => reply_failed!(bind, "'{}' is not bound", key_name(key)),
+        },
+    }
+}
+
+pub(crate) fn unbind_command(
+    mut unbind: ConsoleCommand<UnbindCommand>,
+    mut bindings: ResMut<ConsoleBindings>,
+) {
+    if let Some(Ok(UnbindCommand { key })) = unbind.take() {
+        match parse_key(&key) {
+            Some(key) if bindings.0.remove(&key).is_some() => unbind.ok(),
+            Some(key) => reply_failed!(unbind, "'{}' is not bound", key_name(key)),
+            None => reply_failed!(unbind, "Unknown key '{}'", key),
+        }
+    }
+}
+
+pub(crate) fn bindlist_command(
+    mut bindlist: ConsoleCommand<BindListCommand>,
+    bindings: Res<ConsoleBindings>,
+) {
+    if let Some(Ok(BindListCommand)) = bindlist.take() {
+        for (&key, command) in &bindings.0 {
+            reply!(bindlist, "{} = \"{}\"", key_name(key), command);
+        }
+    }
+}
+
+/// Queues the console lines of the keys pressed this frame, unless the console is open
+pub(crate) fn run_key_bindings(
+    keys: Res<ButtonInput<KeyCode>>,
+    console_open: Res<ConsoleOpen>,
+    bindings: Res<ConsoleBindings>,
+    mut queue: ResMut<CommandQueue>,
+) {
+    if console_open.open {
+        return;
+    }
+    for key in keys.get_just_pressed() {
+        if let Some(command) = bindings.0.get(key) {
+            if let Err(err) = queue.push_line(command, CommandSource::KeyBinding) {
+                warn!("Failed to run the binding of '{}': {err}", key_name(*key));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_app, Marks};
+    use crate::RunConsoleLine;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("F1"), Some(KeyCode::F1));
+        assert_eq!(parse_key("f12"), Some(KeyCode::F12));
+        assert_eq!(parse_key("g"), Some(KeyCode::KeyG));
+        assert_eq!(parse_key("KeyG"), Some(KeyCode::KeyG));
+        assert_eq!(parse_key("1"), Some(KeyCode::Digit1));
+        assert_eq!(parse_key("numpad1"), Some(KeyCode::Numpad1));
+        assert_eq!(parse_key("Key"), None);
+        assert_eq!(parse_key("F13"), None);
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        let mut app = test_app();
+
+        let output = app.run_console_line("bind F1 \"mark a; mark b\"").unwrap();
+        assert_eq!(output.result, Ok(None));
+        let output = app.run_console_line("bind f13 \"mark c\"").unwrap();
+        assert_eq!(output.lines, vec!["Unknown key 'f13'", "[failed]"]);
+        let output = app.run_console_line("bindlist").unwrap();
+        assert_eq!(output.lines, vec!["F1 = \"mark a; mark b\""]);
+
+        // without the input plugin, keys stay just pressed until cleared
+        let press = |app: &mut App| {
+            let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            keys.release(KeyCode::F1);
+            keys.press(KeyCode::F1);
+            app.update();
+            app.world_mut()
+                .resource_mut::<ButtonInput<KeyCode>>()
+                .clear();
+            for _ in 0..5 {
+                app.update();
+            }
+        };
+        press(&mut app);
+        assert_eq!(app.world().resource::<Marks>().0, vec!["a", "b"]);
+
+        // bindings don't run while typing in the console
+        app.world_mut().resource_mut::<ConsoleOpen>().open = true;
+        press(&mut app);
+        assert_eq!(app.world().resource::<Marks>().0, vec!["a", "b"]);
+
+        app.world_mut().resource_mut::<ConsoleOpen>().open = false;
+        let output = app.run_console_line("unbind F1").unwrap();
+        assert_eq!(output.result, Ok(None));
+        press(&mut app);
+        assert_eq!(app.world().resource::<Marks>().0, vec!["a", "b"]);
+    }
+}
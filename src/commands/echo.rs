@@ -0,0 +1,19 @@
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::{reply, ConsoleCommand};
+
+/// Prints its arguments, e.g. `echo fov is $fov`
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "echo")]
+pub(crate) struct EchoCommand {
+    /// Text to print
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    text: Vec<String>,
+}
+
+pub(crate) fn echo_command(mut echo: ConsoleCommand<EchoCommand>) {
+    if let Some(Ok(EchoCommand { text })) = echo.take() {
+        reply!(echo, "{}", text.join(" "));
+    }
+}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::queue::CommandQueue;
+use crate::{reply_failed, ConsoleCommand};
+
+/// Executes a file of console commands, one command line per line
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "exec")]
+pub(crate) struct ExecCommand {
+    /// Path of the script
+    file: PathBuf,
+}
+
+pub(crate) fn exec_command(mut exec: ConsoleCommand<ExecCommand>, mut queue: ResMut<CommandQueue>) {
+    if let Some(Ok(ExecCommand { file })) = exec.take() {
+        match std::fs::read_to_string(&file) {
+            Ok(contents) => {
+                if let Err(err) = queue.push_script(&file.display().to_string(), &contents) {
+                    reply_failed!(exec, "{}", err);
+                }
+            }
+            Err(err) => reply_failed!(exec, "Failed to read '{}': {}", file.display(), err),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::{reply, reply_failed, ConsoleCommand, NamedCommand};
+
+/// Prints the input lines containing a pattern, e.g. `entities | grep Enemy`
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "grep")]
+pub(crate) struct GrepCommand {
+    /// Text to search for
+    pattern: String,
+    /// Ignore case
+    #[arg(short, long)]
+    ignore_case: bool,
+    /// Print the lines not containing the pattern instead
+    #[arg(short = 'v', long)]
+    invert_match: bool,
+}
+
+/// Prints the first input lines
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "head")]
+pub(crate) struct HeadCommand {
+    /// Number of lines
+    #[arg(short = 'n', long, default_value_t = 10)]
+    lines: usize,
+}
+
+/// Prints the last input lines
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "tail")]
+pub(crate) struct TailCommand {
+    /// Number of lines
+    #[arg(short = 'n', long, default_value_t = 10)]
+    lines: usize,
+}
+
+/// Counts the input lines
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "count")]
+pub(crate) struct CountCommand;
+
+/// Sorts the input lines
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "sort")]
+pub(crate) struct SortCommand {
+    /// Sort in reverse order
+    #[arg(short, long)]
+    reverse: bool,
+    /// Remove duplicate lines
+    #[arg(short, long)]
+    unique: bool,
+}
+
+/// Takes the lines piped into a filter, failing the command if nothing was piped into it
+fn take_input<T: NamedCommand>(command: &mut ConsoleCommand<T>) -> Option<Vec<String>> {
+    let input = command.input().map(<[String]>::to_vec);
+    if input.is_none() {
+        reply_failed!(
+            command,
+            "`{0}` filters the output of another command, e.g. `help | {0}`",
+            T::name()
+        );
+    }
+    input
+}
+
+pub(crate) fn grep_command(mut grep: ConsoleCommand<GrepCommand>) {
+    let Some(Ok(GrepCommand {
+        pattern,
+        ignore_case,
+        invert_match,
+    })) = grep.take()
+    else {
+        return;
+    };
+    let Some(input) = take_input(&mut grep) else {
+        return;
+    };
+
+    let pattern = if ignore_case {
+        pattern.to_lowercase()
+    } else {
+        pattern
+    };
+    let mut matched = false;
+    for line in input {
+        let found = if ignore_case {
+            line.to_lowercase().contains(&pattern)
+        } else {
+            line.contains(&pattern)
+        };
+        if found != invert_match {
+            matched = true;
+            reply!(grep, "{}", line);
+        }
+    }
+    // like `grep`, fails when no line matched so it can be used with `&&` and `||`
+    if !matched {
+        grep.failed();
+    }
+}
+
+pub(crate) fn head_command(mut head: ConsoleCommand<HeadCommand>) {
+    if let Some(Ok(HeadCommand { lines })) = head.take() {
+        if let Some(input) = take_input(&mut head) {
+            for line in input.into_iter().take(lines) {
+                head.reply(line);
+            }
+        }
+    }
+}
+
+pub(crate) fn tail_command(mut tail: ConsoleCommand<TailCommand>) {
+    if let Some(Ok(TailCommand { lines })) = tail.take() {
+        if let Some(input) = take_input(&mut tail) {
+            let skip = input.len().saturating_sub(lines);
+            for line in input.into_iter().skip(skip) {
+                tail.reply(line);
+            }
+        }
+    }
+}
+
+pub(crate) fn count_command(mut count: ConsoleCommand<CountCommand>) {
+    if let Some(Ok(CountCommand)) = count.take() {
+        if let Some(input) = take_input(&mut count) {
+            count.ok_with(input.len());
+        }
+    }
+}
+
+pub(crate) fn sort_command(mut sort: ConsoleCommand<SortCommand>) {
+    if let Some(Ok(SortCommand { reverse, unique })) = sort.take() {
+        if let Some(mut input) = take_input(&mut sort) {
+            input.sort();
+            if unique {
+                input.dedup();
+            }
+            if reverse {
+                input.reverse();
+            }
+            for line in input {
+                sort.reply(line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::console::ConsoleState;
+    use crate::testing::test_app;
+    use crate::{CommandError, CommandValue, ConsoleVar, ConsoleVars, RunConsoleLine};
+
+    #[test]
+    fn test_pipes() {
+        let mut app = test_app();
+        app.world_mut()
+            .resource_mut::<ConsoleVars>()
+            .register(ConsoleVar::new("bots", 4));
+
+        let output = app.run_console_line("countdown 3 | sort").unwrap();
+        assert_eq!(output.lines, vec!["1", "2", "3"]);
+        // piped lines are only shown by the last command
+        let scrollback = app.world().resource::<ConsoleState>().scrollback.iter();
+        assert_eq!(scrollback.collect::<Vec<_>>(), ["1", "2", "3"]);
+
+        let output = app
+            .run_console_line("countdown 12 | grep 1 | count")
+            .unwrap();
+        assert_eq!(output.lines, vec!["4", "[ok]"]);
+        assert_eq!(output.result, Ok(Some(CommandValue::UInt(4))));
+
+        let output = app.run_console_line("countdown 5 | tail -n 2").unwrap();
+        assert_eq!(output.lines, vec!["2", "1"]);
+        let output = app.run_console_line("countdown 5 | head -n 2").unwrap();
+        assert_eq!(output.lines, vec!["5", "4"]);
+
+        let output = app.run_console_line("bots | grep -v bots").unwrap();
+        assert_eq!(output.lines, vec!["[failed]"]);
+
+        let output = app
+            .run_console_line("fail && echo a | count || echo b | count")
+            .unwrap();
+        assert_eq!(output.lines, vec!["[failed]", "1", "[ok]"]);
+        // only the status of `echo` isn't piped, not lines looking like it
+        let output = app.run_console_line("echo '[ok]' | count").unwrap();
+        assert_eq!(output.result, Ok(Some(CommandValue::UInt(1))));
+
+        let output = app.run_console_line("count").unwrap();
+        assert_eq!(output.result.unwrap_err().code, 1);
+    }
+
+    #[test]
+    fn test_failing_pipe() {
+        let mut app = test_app();
+
+        // the error isn't piped, the pipe fails with it
+        let output = app
+            .run_console_line("fail 'entity not found' | count")
+            .unwrap();
+        assert_eq!(output.lines, vec!["entity not found", "[failed]"]);
+        assert_eq!(output.result, Err(CommandError::new("entity not found")));
+        let scrollback = app.world().resource::<ConsoleState>().scrollback.iter();
+        assert_eq!(
+            scrollback.collect::<Vec<_>>(),
+            ["entity not found", "[failed]"]
+        );
+
+        let output = app
+            .run_console_line("countdown 3 | grep 4 | count || echo none")
+            .unwrap();
+        assert_eq!(output.lines, vec!["[failed]", "none"]);
+    }
+}
@@ -2,7 +2,8 @@ use bevy::prelude::*;
 use clap::Parser;
 
 use crate as bevy_console;
-use crate::{reply, ConsoleCommand, ConsoleConfiguration};
+use crate::commands::alias::ConsoleAliases;
+use crate::{reply, ConsoleCommand, ConsoleConfiguration, ConsoleVars};
 
 /// Prints available arguments and usage
 #[derive(Parser, ConsoleCommand)]
@@ -15,6 +16,8 @@ pub(crate) struct HelpCommand {
 pub(crate) fn help_command(
     mut help: ConsoleCommand<HelpCommand>,
     mut config: ResMut<ConsoleConfiguration>,
+    aliases: Res<ConsoleAliases>,
+    vars: Res<ConsoleVars>,
 ) {
     match help.take() {
         Some(Ok(HelpCommand { command: Some(cmd) })) => match config.commands.get_mut(cmd.as_str())
@@ -22,9 +25,15 @@ pub(crate) fn help_command(
             Some(command_info) => {
                 help.reply(command_info.render_long_help().to_string());
             }
-            None => {
-                reply!(help, "Command '{}' does not exist", cmd);
-            }
+            None => match (aliases.0.get(&cmd), vars.describe(&cmd)) {
+                (Some(command), _) => {
+                    reply!(help, "'{}' is an alias for \"{}\"", cmd, command);
+                }
+                (None, Some(var)) => help.reply(var),
+                (None, None) => {
+                    reply!(help, "Command '{}' does not exist", cmd);
+                }
+            },
         },
         Some(Ok(HelpCommand { command: None })) => {
             debug!("No command received in help");
@@ -45,6 +54,25 @@ pub(crate) fn help_command(
                 ));
                 help.reply(line);
             }
+            if !aliases.0.is_empty() {
+                reply!(help, "Aliases:");
+                let longest_alias_name = aliases.0.keys().map(|name| name.len()).max().unwrap_or(0);
+                for (name, command) in &aliases.0 {
+                    reply!(
+                        help,
+                        "  {name}{} - \"{command}\"",
+                        " ".repeat(longest_alias_name - name.len())
+                    );
+                }
+            }
+            if vars.names().next().is_some() {
+                reply!(help, "Variables:");
+                for name in vars.names() {
+                    if let Some(var) = vars.describe(name) {
+                        reply!(help, "  {var}");
+                    }
+                }
+            }
             help.reply("");
         }
         _ => {}
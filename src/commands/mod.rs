@@ -1,3 +1,12 @@
+pub(crate) mod alias;
+pub(crate) mod bind;
 pub(crate) mod clear;
+pub(crate) mod echo;
+pub(crate) mod exec;
 pub(crate) mod exit;
+pub(crate) mod filters;
 pub(crate) mod help;
+pub(crate) mod reset;
+pub(crate) mod set;
+pub(crate) mod wait;
+pub(crate) mod writeconfig;
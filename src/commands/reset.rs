@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::{reply_failed, ConsoleCommand, ConsoleVars};
+
+/// Resets a console variable to its default value
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "reset")]
+pub(crate) struct ResetCommand {
+    /// Name of the variable
+    name: String,
+}
+
+pub(crate) fn reset_command(
+    mut reset: ConsoleCommand<ResetCommand>,
+    mut vars: ResMut<ConsoleVars>,
+) {
+    if let Some(Ok(ResetCommand { name })) = reset.take() {
+        if let Err(err) = vars.reset(&name) {
+            reply_failed!(reset, "{}", err);
+        }
+    }
+}
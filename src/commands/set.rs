@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::parser::is_valid_name;
+use crate::{reply, reply_failed, ConsoleCommand, ConsoleVars};
+
+/// Sets a variable expanded with $name in console lines, lists user variables when no name is given
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "set")]
+pub(crate) struct SetCommand {
+    /// Name of the variable
+    name: Option<String>,
+    /// Value of the variable
+    value: Option<String>,
+}
+
+/// Removes a user variable
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "unset")]
+pub(crate) struct UnsetCommand {
+    /// Name of the variable
+    name: String,
+}
+
+pub(crate) fn set_command(mut set: ConsoleCommand<SetCommand>, mut vars: ResMut<ConsoleVars>) {
+    match set.take() {
+        Some(Ok(SetCommand {
+            name: Some(name),
+            value: Some(value),
+        })) => {
+            if vars.contains(&name) {
+                match vars.set_string(&name, &value) {
+                    Ok(()) => set.ok(),
+                    Err(err) => reply_failed!(set, "{}", err),
+                }
+            } else if !is_valid_name(&name) {
+                reply_failed!(set, "Invalid variable name '{}'", name);
+            } else {
+                vars.set_user(name, value);
+                set.ok();
+            }
+        }
+        Some(Ok(SetCommand {
+            name: Some(name),
+            value: None,
+        })) => match vars.expand(&name) {
+            Some(value) => reply!(set, "{} = \"{}\"", name, value),
+            None => reply_failed!(set, "Variable '{}' is not set", name),
+        },
+        Some(Ok(SetCommand { name: None, .. })) => {
+            for (name, value) in vars.user_vars() {
+                reply!(set, "{} = \"{}\"", name, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn unset_command(
+    mut unset: ConsoleCommand<UnsetCommand>,
+    mut vars: ResMut<ConsoleVars>,
+) {
+    if let Some(Ok(UnsetCommand { name })) = unset.take() {
+        if vars.unset_user(&name) {
+            unset.ok();
+        } else {
+            reply_failed!(unset, "Variable '{}' is not set", name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{test_app, Marks};
+    use crate::{ConsoleVar, ConsoleVars, RunConsoleLine};
+
+    #[test]
+    fn test_variable_expansion() {
+        let mut app = test_app();
+        app.world_mut()
+            .resource_mut::<ConsoleVars>()
+            .register(ConsoleVar::new("bots", 4));
+
+        let output = app
+            .run_console_line("set boss 'big boss'; mark $boss; echo \"$bots ${boss}es\" '$bots'")
+            .unwrap();
+        assert_eq!(output.lines, vec!["[ok]", "4 big bosses $bots"]);
+        assert_eq!(app.world().resource::<Marks>().0, vec!["big boss"]);
+
+        let output = app.run_console_line("set bots 6; echo $bots").unwrap();
+        assert_eq!(output.lines, vec!["[ok]", "6"]);
+
+        let output = app.run_console_line("unset boss && echo $boss").unwrap();
+        assert_eq!(
+            output.lines,
+            vec!["[ok]", "error: undefined variable 'boss'"]
+        );
+        assert!(output.result.is_err());
+
+        let output = app.run_console_line("set 'big boss' 1").unwrap();
+        assert_eq!(
+            output.lines,
+            vec!["Invalid variable name 'big boss'", "[failed]"]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::{CommandTask, ConsoleCommand};
+
+/// Duration of a `wait`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum WaitDuration {
+    Frames(u32),
+    Seconds(f32),
+}
+
+impl FromStr for WaitDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_suffix('s') {
+            Some(seconds) => match seconds.parse::<f32>() {
+                Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                    Ok(WaitDuration::Seconds(seconds))
+                }
+                _ => Err(format!("invalid number of seconds '{seconds}'")),
+            },
+            None => s
+                .parse()
+                .map(WaitDuration::Frames)
+                .map_err(|_| "expected a number of frames or seconds, e.g. `10` or `2s`".into()),
+        }
+    }
+}
+
+/// Waits a number of frames, or seconds of `Time`, before running the next command
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "wait")]
+pub(crate) struct WaitCommand {
+    /// Number of frames, e.g. `10`, or seconds, e.g. `2s`
+    duration: WaitDuration,
+}
+
+pub(crate) fn wait_command(
+    mut wait: ConsoleCommand<WaitCommand>,
+    time: Res<Time>,
+    mut waiting: Local<Option<(CommandTask, WaitDuration, f32)>>,
+) {
+    if let Some(Ok(WaitCommand { duration })) = wait.take() {
+        *waiting = Some((wait.start_task(), duration, 0.0));
+    }
+
+    let Some((task, duration, elapsed)) = waiting.as_mut() else {
+        return;
+    };
+    let (total, delta) = match *duration {
+        WaitDuration::Frames(frames) => (frames as f32, 1.0),
+        WaitDuration::Seconds(seconds) => (seconds, time.delta_secs()),
+    };
+    if task.is_cancelled() || *elapsed >= total {
+        // dropping the task completes it without printing `[ok]`
+        *waiting = None;
+        return;
+    }
+    *elapsed += delta;
+    task.set_progress(*elapsed / total);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{test_app, Marks};
+    use crate::{CommandQueue, CommandSource, RunConsoleLine};
+
+    #[test]
+    fn test_wait() {
+        let mut app = test_app();
+        app.world_mut()
+            .resource_mut::<CommandQueue>()
+            .push_line(
+                "mark a; wait 5; mark b; wait 1.5s; mark c",
+                CommandSource::Test,
+            )
+            .unwrap();
+
+        let mut marks = Vec::new();
+        for _ in 0..40 {
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(100));
+            app.update();
+            marks.push(app.world().resource::<Marks>().0.len());
+        }
+        let first_frame = |count| marks.iter().position(|&len| len == count).unwrap();
+        assert!(first_frame(2) - first_frame(1) > 5);
+        // 1.5s at 100ms per frame
+        assert!(first_frame(3) - first_frame(2) > 15);
+        assert_eq!(app.world().resource::<Marks>().0, vec!["a", "b", "c"]);
+        assert!(app.world().resource::<CommandQueue>().is_empty());
+
+        let output = app.run_console_line("wait 2x").unwrap();
+        assert_eq!(output.result.unwrap_err().code, 2);
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::{reply_failed, ConsoleCommand, ConsoleConfiguration, ConsoleVars};
+
+/// Saves archived console variables to the config file
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "writeconfig")]
+pub(crate) struct WriteConfigCommand {
+    /// Path of the config file, defaults to the configured config file
+    file: Option<PathBuf>,
+}
+
+pub(crate) fn writeconfig_command(
+    mut writeconfig: ConsoleCommand<WriteConfigCommand>,
+    config: Res<ConsoleConfiguration>,
+    vars: Res<ConsoleVars>,
+) {
+    if let Some(Ok(WriteConfigCommand { file })) = writeconfig.take() {
+        let Some(file) = file.or_else(|| config.config_file.clone()) else {
+            reply_failed!(writeconfig, "No config file configured");
+            return;
+        };
+        match std::fs::write(&file, vars.archived_to_toml()) {
+            Ok(()) => writeconfig.ok(),
+            Err(err) => reply_failed!(writeconfig, "Failed to write '{}': {}", file.display(), err),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use bevy::platform::hash::FixedState;
+use bevy::prelude::*;
+use shlex::Shlex;
+
+use crate::console::{ConsoleCache, ConsoleState};
+use crate::{ConsoleConfiguration, ConsoleOpen, NamedCommand};
+
+/// Completes an argument of a console command at runtime, with access to the world.
+///
+/// Implemented for closures taking `(&mut World, &str)`.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_console::{AddConsoleCompleter, ConsoleCommand};
+/// # use clap::Parser;
+/// /// Despawns an entity by name
+/// #[derive(Parser, ConsoleCommand)]
+/// #[command(name = "despawn")]
+/// struct DespawnCommand {
+///     name: String,
+/// }
+///
+/// App::new().add_console_completer::<DespawnCommand>("name", |world: &mut World, _partial: &str| {
+///     world
+///         .query::<&Name>()
+///         .iter(world)
+///         .map(|name| name.to_string())
+///         .collect()
+/// });
+/// ```
+pub trait ConsoleCompleter: Send + Sync + 'static {
+    /// Returns the possible values for the argument, `partial` is the part of the argument typed so far.
+    ///
+    /// Values which don't start with `partial` are filtered out.
+    fn complete(&self, world: &mut World, partial: &str) -> Vec<String>;
+}
+
+impl<F: Fn(&mut World, &str) -> Vec<String> + Send + Sync + 'static> ConsoleCompleter for F {
+    fn complete(&self, world: &mut World, partial: &str) -> Vec<String> {
+        self(world, partial)
+    }
+}
+
+/// Completers by command name and argument id
+#[derive(Resource, Default)]
+pub(crate) struct ConsoleCompleters(HashMap<(String, String), Arc<dyn ConsoleCompleter>>);
+
+/// Add console completers to a Bevy app.
+pub trait AddConsoleCompleter {
+    /// Adds a completer for the argument `arg` of the command `T`.
+    ///
+    /// `arg` is the clap id of the argument, which is the field name when using the derive.
+    fn add_console_completer<T: NamedCommand>(
+        &mut self,
+        arg: &str,
+        completer: impl ConsoleCompleter,
+    ) -> &mut Self;
+}
+
+impl AddConsoleCompleter for App {
+    fn add_console_completer<T: NamedCommand>(
+        &mut self,
+        arg: &str,
+        completer: impl ConsoleCompleter,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<ConsoleCompleters>()
+            .0
+            .insert((T::name().to_owned(), arg.to_owned()), Arc::new(completer));
+        self
+    }
+}
+
+/// Runs the completer of the argument being typed whenever the buffer changes
+pub(crate) fn update_dynamic_completions(world: &mut World) {
+    if !world.resource::<ConsoleOpen>().open {
+        return;
+    }
+
+    let buf = world.resource::<ConsoleState>().buf.clone();
+    let hash = FixedState::with_seed(42).hash_one(&buf);
+    if world.resource::<ConsoleCache>().dynamic_hash_key == Some(hash) {
+        return;
+    }
+
+    let predictions = dynamic_completions(world, &buf);
+    let mut cache = world.resource_mut::<ConsoleCache>();
+    cache.dynamic_hash_key = Some(hash);
+    cache.dynamic_predictions = predictions;
+}
+
+fn dynamic_completions(world: &mut World, buf: &str) -> Vec<String> {
+    let mut words = Shlex::new(buf).collect::<Vec<_>>();
+    // the word being typed, empty when a new word was started
+    let partial = if buf.ends_with(char::is_whitespace) {
+        String::new()
+    } else {
+        words.pop().unwrap_or_default()
+    };
+    let Some((name, args)) = words.split_first() else {
+        return Vec::new();
+    };
+
+    let Some(arg) = world
+        .resource::<ConsoleConfiguration>()
+        .commands
+        .get(name.as_str())
+        .and_then(|command| completing_arg(command, args))
+    else {
+        return Vec::new();
+    };
+    let Some(completer) = world
+        .resource::<ConsoleCompleters>()
+        .0
+        .get(&(name.clone(), arg))
+        .cloned()
+    else {
+        return Vec::new();
+    };
+
+    let prefix = shlex::try_join(words.iter().map(String::as_str)).unwrap_or_default();
+    completer
+        .complete(world, &partial)
+        .into_iter()
+        .filter(|value| value.starts_with(&partial))
+        .filter_map(|value| {
+            shlex::try_quote(&value)
+                .ok()
+                .map(|value| format!("{prefix} {value}"))
+        })
+        .collect()
+}
+
+/// Finds the id of the argument following `args`, descending into subcommands
+fn completing_arg(mut command: &clap::Command, args: &[String]) -> Option<String> {
+    let mut positional = 0;
+    let mut expects_value = None;
+    for word in args {
+        if expects_value.take().is_some() {
+            continue;
+        }
+
+        let option = if let Some(long) = word.strip_prefix("--") {
+            command.get_opts().find(|arg| arg.get_long() == Some(long))
+        } else if let Some(short) = word.strip_prefix('-').filter(|short| short.len() == 1) {
+            command
+                .get_opts()
+                .find(|arg| arg.get_short().map(String::from).as_deref() == Some(short))
+        } else {
+            if let Some(subcommand) = command.find_subcommand(word) {
+                command = subcommand;
+                positional = 0;
+            } else {
+                positional += 1;
+            }
+            continue;
+        };
+
+        if let Some(option) = option.filter(|option| option.get_action().takes_values()) {
+            expects_value = Some(option);
+        }
+    }
+
+    match expects_value {
+        Some(option) => Some(option.get_id().to_string()),
+        None => command
+            .get_positionals()
+            .nth(positional)
+            .map(|arg| arg.get_id().to_string()),
+    }
+}
+
+/// Completion sequences for a command generated from its clap definition,
+/// e.g. `spawn`, `spawn --force`, `spawn enemy` and `spawn boss` for a command taking a `value_enum`.
+pub(crate) fn command_completions(prefix: &str, command: &clap::Command) -> Vec<String> {
+    let mut completions = vec![prefix.to_owned()];
+
+    let mut positionals = command.get_positionals().filter(|arg| !arg.is_hide_set());
+    if let Some(arg) = positionals.next() {
+        for value in arg.get_possible_values() {
+            if !value.is_hide_set() {
+                completions.push(format!("{prefix} {}", value.get_name()));
+            }
+        }
+    }
+
+    for arg in command.get_opts() {
+        if arg.is_hide_set() {
+            continue;
+        }
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        completions.push(format!("{prefix} --{long}"));
+        if arg.get_action().takes_values() {
+            for value in arg.get_possible_values() {
+                if !value.is_hide_set() {
+                    completions.push(format!("{prefix} --{long} {}", value.get_name()));
+                }
+            }
+        }
+    }
+
+    for subcommand in command.get_subcommands() {
+        if !subcommand.is_hide_set() {
+            completions.extend(command_completions(
+                &format!("{prefix} {}", subcommand.get_name()),
+                subcommand,
+            ));
+        }
+    }
+
+    completions
+}
+
+/// How typed text is matched against completions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompletionMode {
+    /// Suggest completions starting with the typed text
+    #[default]
+    Prefix,
+    /// Suggest completions containing the typed characters in order, e.g. `spawn` suggests `debug_spawn_wave`.
+    /// Prefix matches are ranked first.
+    Fuzzy,
+}
+
+/// A completion matching a query, see [`fuzzy_match`]
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct FuzzyMatch {
+    pub(crate) score: i64,
+    /// Char indices of the matched characters in the completion
+    pub(crate) indices: Vec<usize>,
+}
+
+/// Matches the chars of `query` as a case-insensitive subsequence of `candidate`.
+///
+/// Consecutive matches and matches at the start of a word score higher,
+/// a candidate starting with the query always scores higher than one that doesn't.
+pub(crate) fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query = query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .collect::<Vec<_>>();
+    let candidate = candidate
+        .chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect::<Vec<_>>();
+    let first = *query.first()?;
+
+    // try every occurrence of the first char, greedily matching the rest
+    let mut best: Option<FuzzyMatch> = None;
+    for start in (0..candidate.len()).filter(|&i| candidate[i] == first) {
+        let mut indices = vec![start];
+        let mut next = start + 1;
+        for &c in &query[1..] {
+            match candidate[next..].iter().position(|&other| other == c) {
+                Some(offset) => {
+                    indices.push(next + offset);
+                    next += offset + 1;
+                }
+                None => break,
+            }
+        }
+        if indices.len() < query.len() {
+            // no later start can match more chars
+            break;
+        }
+
+        let mut score = 0;
+        for (i, &index) in indices.iter().enumerate() {
+            score += 1;
+            if i > 0 && indices[i - 1] + 1 == index {
+                score += 5;
+            }
+            if index == 0 || matches!(candidate[index - 1], ' ' | '_' | '-' | '.' | ':' | '/') {
+                score += 3;
+            }
+        }
+        score -= (indices[indices.len() - 1] - start + 1 - indices.len()) as i64;
+        if indices.iter().copied().eq(0..query.len()) {
+            score += 1000;
+        }
+
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(FuzzyMatch { score, indices });
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+
+    use super::*;
+    use crate as bevy_console;
+    use crate::ConsoleCommand;
+
+    #[derive(Clone, ValueEnum)]
+    enum Kind {
+        Enemy,
+        Boss,
+    }
+
+    #[derive(Parser, ConsoleCommand)]
+    #[command(name = "spawn")]
+    struct SpawnCommand {
+        #[arg(value_enum)]
+        kind: Kind,
+        /// Spawns even if the area is blocked
+        #[arg(long)]
+        force: bool,
+        #[arg(long, value_enum)]
+        team: Option<Kind>,
+        #[command(subcommand)]
+        wave: Option<Wave>,
+    }
+
+    #[derive(Subcommand)]
+    enum Wave {
+        Wave {
+            #[arg(long)]
+            size: usize,
+        },
+    }
+
+    #[test]
+    fn test_command_completions() {
+        let command = SpawnCommand::command();
+        assert_eq!(
+            command_completions("spawn", &command),
+            vec![
+                "spawn",
+                "spawn enemy",
+                "spawn boss",
+                "spawn --force",
+                "spawn --team",
+                "spawn --team enemy",
+                "spawn --team boss",
+                "spawn wave",
+                "spawn wave --size",
+            ]
+        );
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        Shlex::new(line).collect()
+    }
+
+    #[test]
+    fn test_completing_arg() {
+        let command = SpawnCommand::command();
+        assert_eq!(completing_arg(&command, &[]), Some("kind".into()));
+        assert_eq!(
+            completing_arg(&command, &words("--team")),
+            Some("team".into())
+        );
+        assert_eq!(
+            completing_arg(&command, &words("--force")),
+            Some("kind".into())
+        );
+        assert_eq!(
+            completing_arg(&command, &words("--team boss")),
+            Some("kind".into())
+        );
+        assert_eq!(completing_arg(&command, &words("enemy")), None);
+        assert_eq!(
+            completing_arg(&command, &words("enemy wave --size")),
+            Some("size".into())
+        );
+    }
+
+    #[test]
+    fn test_dynamic_completions() {
+        let mut app = App::new();
+        app.init_resource::<ConsoleConfiguration>()
+            .add_console_completer::<SpawnCommand>("team", |_: &mut World, _: &str| {
+                vec![
+                    "red".to_string(),
+                    "blue team".to_string(),
+                    "rogue".to_string(),
+                ]
+            });
+        app.world_mut()
+            .resource_mut::<ConsoleConfiguration>()
+            .commands
+            .insert("spawn", SpawnCommand::command());
+
+        let world = app.world_mut();
+        assert_eq!(
+            dynamic_completions(world, "spawn --team "),
+            vec![
+                "spawn --team red",
+                "spawn --team 'blue team'",
+                "spawn --team rogue"
+            ]
+        );
+        assert_eq!(
+            dynamic_completions(world, "spawn --team r"),
+            vec!["spawn --team red", "spawn --team rogue"]
+        );
+        assert!(dynamic_completions(world, "spawn ").is_empty());
+        assert!(dynamic_completions(world, "spawn --team red ").is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        let spawn = fuzzy_match("spawn", "debug_spawn_wave").unwrap();
+        assert_eq!(spawn.indices, vec![6, 7, 8, 9, 10]);
+        assert_eq!(
+            fuzzy_match("dsw", "debug_spawn_wave").unwrap().indices,
+            vec![0, 6, 9]
+        );
+        assert_eq!(fuzzy_match("SPW", "spawn").unwrap().indices, vec![0, 1, 3]);
+        assert_eq!(fuzzy_match("wsp", "debug_spawn_wave"), None);
+        assert_eq!(fuzzy_match("", "spawn"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_match_ranking() {
+        let score = |candidate| fuzzy_match("spawn", candidate).unwrap().score;
+        // prefix matches first, then consecutive matches, then scattered matches
+        assert!(score("spawn_boss") > score("debug_spawn_wave"));
+        assert!(score("debug_spawn_wave") > score("debug_s_p_a_w_n"));
+        assert!(score("debug_spawn") > score("debugspawn"));
+    }
+}
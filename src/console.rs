@@ -1,7 +1,7 @@
 use bevy::ecs::resource::Resource;
 use bevy::ecs::{
     component::Tick,
-    system::{ScheduleSystem, SystemMeta, SystemParam},
+    system::{ScheduleSystem, SystemId, SystemMeta, SystemParam},
     world::unsafe_world_cell::UnsafeWorldCell,
 };
 use bevy::platform::hash::FixedState;
@@ -15,15 +15,27 @@ use bevy_egui::{
 };
 use clap::{CommandFactory, FromArgMatches};
 use core::str;
+use regex::{Regex, RegexBuilder};
 use shlex::Shlex;
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fs::OpenOptions;
 use std::hash::BuildHasher;
+use std::io::{ErrorKind, Write};
 use std::marker::PhantomData;
 use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use trie_rs::Trie;
 
 use crate::{
     color::{parse_ansi_styled_str, TextFormattingOverride},
+    completion::{fuzzy_match, CompletionMode},
+    console_fn::{
+        fn_command, register_exclusive_command, ConsoleFn, ConsoleFnResult, ExclusiveHandler,
+    },
+    queue::CommandQueue,
+    result::{result_lines, CommandError, CommandResult, CommandValue},
+    task::{CommandTask, TaskStarted},
     ConsoleSet,
 };
 
@@ -31,6 +43,10 @@ type ConsoleCommandEnteredReaderSystemParam = EventReader<'static, 'static, Cons
 
 type PrintConsoleLineWriterSystemParam = EventWriter<'static, PrintConsoleLine>;
 
+type ConsoleCommandCompletedWriterSystemParam = EventWriter<'static, ConsoleCommandCompleted>;
+
+type TaskStartedWriterSystemParam = EventWriter<'static, TaskStarted>;
+
 /// A super-trait for command like structures
 pub trait Command: NamedCommand + CommandFactory + FromArgMatches + Sized + Resource {}
 impl<T: NamedCommand + CommandFactory + FromArgMatches + Sized + Resource> Command for T {}
@@ -69,10 +85,16 @@ pub trait NamedCommand {
 /// ```
 pub struct ConsoleCommand<'w, T> {
     command: Option<Result<T, clap::Error>>,
+    /// Invocation of the entered command, replies are tagged with it
+    invocation: Option<Invocation>,
+    /// Output of the previous command, if piped into the entered command
+    input: Option<Vec<String>>,
     console_line: EventWriter<'w, PrintConsoleLine>,
+    completed: EventWriter<'w, ConsoleCommandCompleted>,
+    task_started: EventWriter<'w, TaskStarted>,
 }
 
-impl<T> ConsoleCommand<'_, T> {
+impl<T: NamedCommand> ConsoleCommand<'_, T> {
     /// Returns Some(T) if the command was executed and arguments were valid.
     ///
     /// This method should only be called once.
@@ -81,30 +103,75 @@ impl<T> ConsoleCommand<'_, T> {
         mem::take(&mut self.command)
     }
 
+    /// Lines printed by the previous command when piped into this one, e.g. the output of `entities` in `entities | grep Enemy`.
+    ///
+    /// Returns `None` if nothing was piped into the command.
+    pub fn input(&self) -> Option<&[String]> {
+        self.input.as_deref()
+    }
+
+    /// Complete the command with a result, printing the value or error in the console.
+    ///
+    /// The result is sent as a [`ConsoleCommandCompleted`] event,
+    /// and is used when chaining commands with `&&` and `||`.
+    pub fn complete(&mut self, result: CommandResult) {
+        for mut line in result_lines(&result) {
+            line.invocation = self.invocation;
+            self.console_line.write(line);
+        }
+        let mut completed = ConsoleCommandCompleted::new(T::name(), result);
+        completed.invocation = self.invocation;
+        self.completed.write(completed);
+    }
+
     /// Print `[ok]` in the console.
+    ///
+    /// This also marks the command as succeeded, which is used when chaining commands with `&&` and `||`.
     pub fn ok(&mut self) {
-        self.console_line
-            .write(PrintConsoleLine::new("[ok]".into()));
+        self.complete(Ok(None));
+    }
+
+    /// Print a value followed by `[ok]` in the console, completing the command with the value.
+    pub fn ok_with(&mut self, value: impl Into<CommandValue>) {
+        self.complete(Ok(Some(value.into())));
     }
 
     /// Print `[failed]` in the console.
+    ///
+    /// This also marks the command as failed, which is used when chaining commands with `&&` and `||`.
     pub fn failed(&mut self) {
-        self.console_line
-            .write(PrintConsoleLine::new("[failed]".into()));
+        self.complete(Err(CommandError::new("")));
+    }
+
+    /// Print an error followed by `[failed]` in the console, completing the command with the error.
+    pub fn failed_with(&mut self, error: impl Into<CommandError>) {
+        self.complete(Err(error.into()));
+    }
+
+    /// Start a task to keep the command running over several frames.
+    ///
+    /// The console waits for the task to complete before executing the next command,
+    /// see [`CommandTask`] for reporting progress and handling cancellation.
+    pub fn start_task(&mut self) -> CommandTask {
+        let task = CommandTask::new(T::name(), self.invocation);
+        self.task_started.write(TaskStarted(task.clone()));
+        task
     }
 
     /// Print a reply in the console.
     ///
     /// See [`reply!`](crate::reply) for usage with the [`format!`] syntax.
     pub fn reply(&mut self, msg: impl Into<String>) {
-        self.console_line.write(PrintConsoleLine::new(msg.into()));
+        let mut line = PrintConsoleLine::new(msg.into());
+        line.invocation = self.invocation;
+        self.console_line.write(line);
     }
 
     /// Print a reply in the console followed by `[ok]`.
     ///
     /// See [`reply_ok!`](crate::reply_ok) for usage with the [`format!`] syntax.
     pub fn reply_ok(&mut self, msg: impl Into<String>) {
-        self.console_line.write(PrintConsoleLine::new(msg.into()));
+        self.reply(msg);
         self.ok();
     }
 
@@ -112,8 +179,7 @@ impl<T> ConsoleCommand<'_, T> {
     ///
     /// See [`reply_failed!`](crate::reply_failed) for usage with the [`format!`] syntax.
     pub fn reply_failed(&mut self, msg: impl Into<String>) {
-        self.console_line.write(PrintConsoleLine::new(msg.into()));
-        self.failed();
+        self.failed_with(msg.into());
     }
 }
 
@@ -121,6 +187,8 @@ pub struct ConsoleCommandState<T> {
     #[allow(clippy::type_complexity)]
     event_reader: <ConsoleCommandEnteredReaderSystemParam as SystemParam>::State,
     console_line: <PrintConsoleLineWriterSystemParam as SystemParam>::State,
+    completed: <ConsoleCommandCompletedWriterSystemParam as SystemParam>::State,
+    task_started: <TaskStartedWriterSystemParam as SystemParam>::State,
     marker: PhantomData<T>,
 }
 
@@ -131,9 +199,13 @@ unsafe impl<T: Command> SystemParam for ConsoleCommand<'_, T> {
     fn init_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::State {
         let event_reader = ConsoleCommandEnteredReaderSystemParam::init_state(world, system_meta);
         let console_line = PrintConsoleLineWriterSystemParam::init_state(world, system_meta);
+        let completed = ConsoleCommandCompletedWriterSystemParam::init_state(world, system_meta);
+        let task_started = TaskStartedWriterSystemParam::init_state(world, system_meta);
         ConsoleCommandState {
             event_reader,
             console_line,
+            completed,
+            task_started,
             marker: PhantomData,
         }
     }
@@ -157,9 +229,25 @@ unsafe impl<T: Command> SystemParam for ConsoleCommand<'_, T> {
             world,
             change_tick,
         );
+        let mut completed = ConsoleCommandCompletedWriterSystemParam::get_param(
+            &mut state.completed,
+            system_meta,
+            world,
+            change_tick,
+        );
+        let task_started = TaskStartedWriterSystemParam::get_param(
+            &mut state.task_started,
+            system_meta,
+            world,
+            change_tick,
+        );
 
+        let mut invocation = None;
+        let mut input = None;
         let command = event_reader.read().find_map(|command| {
             if T::name() == command.command_name {
+                invocation = Some(command.invocation);
+                input.clone_from(&command.input);
                 let clap_command = T::command().no_binary_name(true);
                 // .color(clap::ColorChoice::Always);
                 let arg_matches = clap_command.try_get_matches_from(command.args.iter());
@@ -174,7 +262,21 @@ unsafe impl<T: Command> SystemParam for ConsoleCommand<'_, T> {
                         return Some(T::from_arg_matches(&matches));
                     }
                     Err(err) => {
-                        console_line.write(PrintConsoleLine::new(err.to_string()));
+                        let line = PrintConsoleLine::new(err.to_string())
+                            .with_invocation(command.invocation);
+                        // `--help` and `--version` are reported as errors by clap, but are not failures
+                        if err.use_stderr() {
+                            console_line.write(line.as_error());
+                            completed.write(
+                                ConsoleCommandCompleted::new(
+                                    T::name(),
+                                    Err(CommandError::new(err.to_string()).with_code(2)),
+                                )
+                                .with_invocation(command.invocation),
+                            );
+                        } else {
+                            console_line.write(line);
+                        }
                         return Some(Err(err));
                     }
                 }
@@ -184,10 +286,45 @@ unsafe impl<T: Command> SystemParam for ConsoleCommand<'_, T> {
 
         ConsoleCommand {
             command,
+            invocation,
+            input,
             console_line,
+            completed,
+            task_started,
         }
     }
 }
+/// Identifies a console line queued for execution, shared by every command the line runs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InvocationId(pub u64);
+
+/// Where a console line was entered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CommandSource {
+    /// The console window
+    #[default]
+    Console,
+    /// A startup script or command
+    Script,
+    /// A remote client, identified by the front-end
+    Remote(u64),
+    /// A test, see [`RunConsoleLine`](crate::RunConsoleLine)
+    Test,
+    /// Game code, see [`ConsoleRunner`](crate::ConsoleRunner)
+    Code,
+    /// A key bound with `bind`
+    KeyBinding,
+}
+
+/// The console line a command was run from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Invocation {
+    /// Id of the console line
+    pub id: InvocationId,
+    /// Where the console line was entered
+    pub source: CommandSource,
+}
+
 /// Parsed raw console command into `command` and `args`.
 #[derive(Clone, Debug, Event)]
 pub struct ConsoleCommandEntered {
@@ -195,6 +332,40 @@ pub struct ConsoleCommandEntered {
     pub command_name: String,
     /// Raw parsed arguments
     pub args: Vec<String>,
+    /// Console line the command was run from
+    pub invocation: Invocation,
+    /// Lines printed by the previous command when piped into this one with `|`
+    pub input: Option<Vec<String>>,
+}
+
+/// Emitted when a console command reports its result, e.g. with [`ConsoleCommand::ok`] or [`ConsoleCommand::failed`].
+///
+/// Commands which don't report a result are assumed to have succeeded.
+#[derive(Clone, Debug, Event, PartialEq)]
+pub struct ConsoleCommandCompleted {
+    /// Name of the command which completed
+    pub command_name: String,
+    /// Reported result
+    pub result: CommandResult,
+    /// Console line the command was run from
+    pub invocation: Option<Invocation>,
+}
+
+impl ConsoleCommandCompleted {
+    /// Creates a new command completion.
+    pub fn new(command_name: impl Into<String>, result: CommandResult) -> Self {
+        Self {
+            command_name: command_name.into(),
+            result,
+            invocation: None,
+        }
+    }
+
+    /// Marks the completion as belonging to a command of the given console line.
+    pub fn with_invocation(mut self, invocation: Invocation) -> Self {
+        self.invocation = Some(invocation);
+        self
+    }
 }
 
 /// Events to print to the console.
@@ -202,12 +373,43 @@ pub struct ConsoleCommandEntered {
 pub struct PrintConsoleLine {
     /// Console line
     pub line: String,
+    /// Console line whose command printed this line, `None` for lines not printed by a command
+    pub invocation: Option<Invocation>,
+    /// Whether the line reports an error of the command, e.g. its message or `[failed]`.
+    /// Errors are never piped into the next command
+    pub error: bool,
+    /// Whether the line reports the status of the command, i.e. `[ok]` or `[failed]`.
+    /// The status isn't part of the output of the command, so it's never piped either
+    pub status: bool,
 }
 
 impl PrintConsoleLine {
     /// Creates a new console line to print.
     pub const fn new(line: String) -> Self {
-        Self { line }
+        Self {
+            line,
+            invocation: None,
+            error: false,
+            status: false,
+        }
+    }
+
+    /// Marks the line as reporting an error of the command.
+    pub const fn as_error(mut self) -> Self {
+        self.error = true;
+        self
+    }
+
+    /// Marks the line as reporting the status of the command.
+    pub const fn as_status(mut self) -> Self {
+        self.status = true;
+        self
+    }
+
+    /// Marks the line as printed by a command of the given console line.
+    pub const fn with_invocation(mut self, invocation: Invocation) -> Self {
+        self.invocation = Some(invocation);
+        self
     }
 }
 
@@ -228,6 +430,10 @@ pub struct ConsoleConfiguration {
     pub commands: BTreeMap<&'static str, clap::Command>,
     /// Number of commands to store in history
     pub history_size: usize,
+    /// File the command history is loaded from at startup and saved to after each command
+    pub history_file: Option<PathBuf>,
+    /// Number of lines kept in the scrollback, older lines are dropped
+    pub scrollback_size: usize,
     /// Line prefix symbol
     pub symbol: String,
     /// allows window to be collpased
@@ -246,13 +452,25 @@ pub struct ConsoleConfiguration {
     pub foreground_color: Color32,
     /// Number of suggested commands to show
     pub num_suggestions: usize,
+    /// How the input is matched against suggested commands
+    pub completion_mode: CompletionMode,
     /// Blocks mouse from clicking through console
     pub block_mouse: bool,
     /// Blocks keyboard from interacting outside console when active
     pub block_keyboard: bool,
     /// Custom completion sequences,
-    /// for example [vec!["custom", "foo"]], will complete `custom foo` when typing `custom`
+    /// for example [vec!["custom", "foo"]], will complete `custom foo` when typing `custom`.
+    /// Subcommands, flags and possible values of registered commands are completed automatically.
     pub arg_completions: Vec<Vec<String>>,
+    /// Scripts executed with `exec` at startup, once all commands are registered
+    pub startup_scripts: Vec<PathBuf>,
+    /// Console lines executed at startup, after the startup scripts
+    pub startup_commands: Vec<String>,
+    /// Command line flag taking `+cmd` style console commands to execute at startup,
+    /// for example `--console "+map arena +bots 4"`. Set to `None` to ignore the command line.
+    pub command_line_flag: Option<String>,
+    /// TOML file archived console variables are loaded from at startup and saved to with `writeconfig`
+    pub config_file: Option<PathBuf>,
 }
 
 #[derive(Resource, Default)]
@@ -260,9 +478,16 @@ pub struct ConsoleCache {
     /// Trie used for completions, autogenerated from registered console commands
     /// this probably should operate over references to save memory, but this is convenient for now
     pub(crate) commands_trie: Option<Trie<u8>>,
+    /// Every sequence in the trie, searched in [`CompletionMode::Fuzzy`]
+    pub(crate) completions: Vec<String>,
     pub(crate) predictions_hash_key: Option<u64>,
     pub(crate) predictions_cache: Vec<String>,
+    /// Char indices of the characters matching the buffer, for each prediction
+    pub(crate) prediction_highlights: Vec<Vec<usize>>,
     pub(crate) prediction_matches_buffer: bool,
+    /// Completions from [`ConsoleCompleter`](crate::ConsoleCompleter)s for the current buffer
+    pub(crate) dynamic_hash_key: Option<u64>,
+    pub(crate) dynamic_predictions: Vec<String>,
 }
 
 impl Default for ConsoleConfiguration {
@@ -275,6 +500,8 @@ impl Default for ConsoleConfiguration {
             width: 800.0,
             commands: BTreeMap::new(),
             history_size: 20,
+            history_file: None,
+            scrollback_size: 10_000,
             symbol: "$ ".to_owned(),
             collapsible: false,
             title_name: "Console".to_string(),
@@ -284,9 +511,14 @@ impl Default for ConsoleConfiguration {
             background_color: Color32::from_black_alpha(102),
             foreground_color: Color32::LIGHT_GRAY,
             num_suggestions: 4,
+            completion_mode: CompletionMode::Prefix,
             block_mouse: false,
             block_keyboard: false,
             arg_completions: Default::default(),
+            startup_scripts: Vec::new(),
+            startup_commands: Vec::new(),
+            command_line_flag: Some("--console".to_owned()),
+            config_file: None,
         }
     }
 }
@@ -301,6 +533,8 @@ impl Clone for ConsoleConfiguration {
             width: self.width,
             commands: self.commands.clone(),
             history_size: self.history_size,
+            history_file: self.history_file.clone(),
+            scrollback_size: self.scrollback_size,
             symbol: self.symbol.clone(),
             arg_completions: self.arg_completions.clone(),
             collapsible: false,
@@ -311,8 +545,13 @@ impl Clone for ConsoleConfiguration {
             background_color: Color32::from_black_alpha(102),
             foreground_color: Color32::LIGHT_GRAY,
             num_suggestions: 4,
+            completion_mode: self.completion_mode,
             block_mouse: self.block_mouse,
             block_keyboard: self.block_keyboard,
+            startup_scripts: self.startup_scripts.clone(),
+            startup_commands: self.startup_commands.clone(),
+            command_line_flag: self.command_line_flag.clone(),
+            config_file: self.config_file.clone(),
         }
     }
 }
@@ -323,6 +562,10 @@ pub trait AddConsoleCommand {
     ///
     /// This registers the console command so it will print with the built-in `help` console command.
     ///
+    /// The system only runs on frames the command is entered, or while a task it started with
+    /// [`ConsoleCommand::start_task`] is running. Work done every frame, or progress kept in a `Local`
+    /// between frames, belongs in a separate system or must be driven by a task.
+    ///
     /// # Example
     ///
     /// ```
@@ -343,6 +586,59 @@ pub trait AddConsoleCommand {
         &mut self,
         system: impl IntoScheduleConfigs<ScheduleSystem, Params>,
     ) -> &mut Self;
+
+    /// Add a function as a console command, taking one positional argument per function argument.
+    ///
+    /// The function is run as a one-shot system with exclusive world access when the command is entered.
+    /// Returning an `Err` prints the error and reports the command as failed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_console::AddConsoleCommand;
+    /// # #[derive(Resource)]
+    /// # struct Health(u32);
+    /// App::new().add_console_fn("heal", "Heal the player", |amount: u32, world: &mut World| {
+    ///     world.resource_mut::<Health>().0 += amount;
+    /// });
+    /// ```
+    fn add_console_fn<Marker>(
+        &mut self,
+        name: &'static str,
+        about: &'static str,
+        function: impl ConsoleFn<Marker>,
+    ) -> &mut Self;
+
+    /// Add a console command with a handler taking the parsed command and exclusive world access.
+    ///
+    /// The handler is run as a one-shot system when the command is entered,
+    /// so it can spawn and despawn entities, insert resources or run other systems synchronously.
+    /// Returning an `Err` prints the error and reports the command as failed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_console::{AddConsoleCommand, ConsoleCommand};
+    /// # use clap::Parser;
+    /// App::new().add_exclusive_console_command(|spawn: SpawnCommand, world: &mut World| {
+    ///     for _ in 0..spawn.count {
+    ///         world.spawn(Name::new("enemy"));
+    ///     }
+    /// });
+    /// #
+    /// # /// Spawns enemies
+    /// # #[derive(Parser, ConsoleCommand)]
+    /// # #[command(name = "spawn")]
+    /// # struct SpawnCommand {
+    /// #     count: usize,
+    /// # }
+    /// ```
+    fn add_exclusive_console_command<T: Command, R: ConsoleFnResult>(
+        &mut self,
+        handler: impl Fn(T, &mut World) -> R + Send + Sync + 'static,
+    ) -> &mut Self;
 }
 
 impl AddConsoleCommand for App {
@@ -364,7 +660,215 @@ impl AddConsoleCommand for App {
         };
 
         self.add_systems(Startup, sys.in_set(ConsoleSet::Startup))
-            .add_systems(Update, system.in_set(ConsoleSet::Commands))
+            .add_systems(
+                Update,
+                system
+                    .in_set(ConsoleSet::Commands)
+                    .run_if(command_entered::<T>),
+            )
+    }
+
+    fn add_console_fn<Marker>(
+        &mut self,
+        name: &'static str,
+        about: &'static str,
+        function: impl ConsoleFn<Marker>,
+    ) -> &mut Self {
+        let command = fn_command(name, about, &function);
+        let handler: ExclusiveHandler =
+            Box::new(move |matches, world| function.run(matches, world));
+        let mut registration = Some((command, handler));
+        let sys = move |world: &mut World| {
+            if let Some((command, handler)) = registration.take() {
+                register_exclusive_command(world, name, command, handler);
+            }
+        };
+
+        self.add_systems(Startup, sys.in_set(ConsoleSet::Startup))
+    }
+
+    fn add_exclusive_console_command<T: Command, R: ConsoleFnResult>(
+        &mut self,
+        handler: impl Fn(T, &mut World) -> R + Send + Sync + 'static,
+    ) -> &mut Self {
+        let handler: ExclusiveHandler = Box::new(move |matches, world| {
+            let command = T::from_arg_matches(matches)
+                .map_err(|err| CommandError::new(err.to_string()).with_code(2))?;
+            handler(command, world).into_result()
+        });
+        let mut registration = Some(handler);
+        let sys = move |world: &mut World| {
+            if let Some(handler) = registration.take() {
+                let command = T::command().no_binary_name(true);
+                register_exclusive_command(world, T::name(), command, handler);
+            }
+        };
+
+        self.add_systems(Startup, sys.in_set(ConsoleSet::Startup))
+    }
+}
+
+/// Names of the commands entered this frame or running a task, so only the systems of those commands run
+#[derive(Resource, Default)]
+pub(crate) struct EnteredCommands(HashSet<String>);
+
+/// Indexes the [`ConsoleCommandEntered`] events of this frame and the running tasks by command name
+pub(crate) fn index_entered_commands(
+    mut command_entered: EventReader<ConsoleCommandEntered>,
+    queue: Res<CommandQueue>,
+    systems: Res<ConsoleCommandSystems>,
+    mut entered: ResMut<EnteredCommands>,
+) {
+    entered.0.clear();
+    entered.0.extend(
+        command_entered
+            .read()
+            .map(|command| command.command_name.clone()),
+    );
+    // commands keep running while their task does
+    entered
+        .0
+        .extend(queue.tasks.iter().map(|task| task.progress().0));
+    // commands replaced at runtime are only handled by their one-shot system
+    entered
+        .0
+        .retain(|name| !systems.0.contains_key(name.as_str()));
+}
+
+/// Run condition of command systems, true if the command was entered this frame or is running a task
+fn command_entered<T: NamedCommand>(entered: Res<EnteredCommands>) -> bool {
+    entered.0.contains(T::name())
+}
+
+/// Handlers of console commands registered at runtime, run as one-shot systems when entered
+#[derive(Resource, Default)]
+pub(crate) struct ConsoleCommandSystems(pub(crate) BTreeMap<&'static str, SystemId>);
+
+/// Add and remove console commands at runtime, e.g. when a game mode plugin is loaded or a `State` is entered.
+///
+/// `help`, dispatch and completion are updated on the next frame.
+pub trait RegisterConsoleCommand {
+    /// Register a console command with a given system, replacing any command of the same name.
+    ///
+    /// The system is run as a one-shot system whenever the command is entered.
+    /// The system of a command added with [`AddConsoleCommand`] stays in the schedule,
+    /// but no longer runs while the command is replaced.
+    /// A task started with [`ConsoleCommand::start_task`] can't be driven by the system,
+    /// as it doesn't run on the following frames. Complete the task from another system or a thread instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bevy::prelude::*;
+    /// # use bevy_console::{ConsoleCommand, RegisterConsoleCommand};
+    /// # use clap::Parser;
+    /// fn enter_arena(mut commands: Commands) {
+    ///     commands.register_console_command::<BotsCommand, _>(bots_command);
+    /// }
+    ///
+    /// fn exit_arena(mut commands: Commands) {
+    ///     commands.unregister_console_command("bots");
+    /// }
+    /// #
+    /// # /// Sets the number of bots
+    /// # #[derive(Parser, ConsoleCommand)]
+    /// # #[command(name = "bots")]
+    /// # struct BotsCommand {
+    /// #     count: usize,
+    /// # }
+    /// #
+    /// # fn bots_command(mut bots: ConsoleCommand<BotsCommand>) {}
+    /// ```
+    fn register_console_command<T: Command, Marker>(
+        &mut self,
+        system: impl IntoSystem<(), (), Marker> + Send + 'static,
+    ) -> &mut Self;
+
+    /// Remove a console command, including commands added with [`AddConsoleCommand`].
+    ///
+    /// The systems of commands added with [`AddConsoleCommand`] stay in the schedule,
+    /// but will no longer receive the command.
+    fn unregister_console_command(&mut self, name: &str) -> &mut Self;
+}
+
+/// Registers `command`, dispatched to the one-shot system `id`
+pub(crate) fn insert_command_system(
+    world: &mut World,
+    name: &'static str,
+    command: clap::Command,
+    id: SystemId,
+) {
+    world
+        .get_resource_or_init::<ConsoleConfiguration>()
+        .commands
+        .insert(name, command);
+    let previous = world
+        .get_resource_or_init::<ConsoleCommandSystems>()
+        .0
+        .insert(name, id);
+    if let Some(previous) = previous {
+        warn!("console command '{name}' already registered and was overwritten");
+        let _ = world.unregister_system(previous);
+    }
+}
+
+impl RegisterConsoleCommand for World {
+    fn register_console_command<T: Command, Marker>(
+        &mut self,
+        system: impl IntoSystem<(), (), Marker> + Send + 'static,
+    ) -> &mut Self {
+        let id = self.register_system(system);
+        insert_command_system(self, T::name(), T::command().no_binary_name(true), id);
+        self
+    }
+
+    fn unregister_console_command(&mut self, name: &str) -> &mut Self {
+        if let Some(mut config) = self.get_resource_mut::<ConsoleConfiguration>() {
+            config.commands.remove(name);
+        }
+        let id = self
+            .get_resource_mut::<ConsoleCommandSystems>()
+            .and_then(|mut systems| systems.0.remove(name));
+        if let Some(id) = id {
+            let _ = self.unregister_system(id);
+        }
+        self
+    }
+}
+
+impl RegisterConsoleCommand for Commands<'_, '_> {
+    fn register_console_command<T: Command, Marker>(
+        &mut self,
+        system: impl IntoSystem<(), (), Marker> + Send + 'static,
+    ) -> &mut Self {
+        self.queue(move |world: &mut World| {
+            world.register_console_command::<T, Marker>(system);
+        });
+        self
+    }
+
+    fn unregister_console_command(&mut self, name: &str) -> &mut Self {
+        let name = name.to_owned();
+        self.queue(move |world: &mut World| {
+            world.unregister_console_command(&name);
+        });
+        self
+    }
+}
+
+impl RegisterConsoleCommand for App {
+    fn register_console_command<T: Command, Marker>(
+        &mut self,
+        system: impl IntoSystem<(), (), Marker> + Send + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .register_console_command::<T, Marker>(system);
+        self
+    }
+
+    fn unregister_console_command(&mut self, name: &str) -> &mut Self {
+        self.world_mut().unregister_console_command(name);
+        self
     }
 }
 
@@ -378,20 +882,161 @@ pub struct ConsoleOpen {
 #[derive(Resource)]
 pub(crate) struct ConsoleState {
     pub(crate) buf: String,
-    pub(crate) scrollback: Vec<String>,
+    pub(crate) scrollback: Scrollback,
     pub(crate) history: VecDeque<String>,
     pub(crate) history_index: usize,
     pub(crate) suggestion_index: Option<usize>,
+    pub(crate) reverse_search: Option<ReverseSearch>,
+    pub(crate) scrollback_search: Option<ScrollbackSearch>,
+    /// Moves the input cursor to the end of the buffer on the next frame
+    pub(crate) cursor_to_end: bool,
+}
+
+/// State of the reverse incremental history search, opened with Ctrl+R
+#[derive(Default)]
+pub(crate) struct ReverseSearch {
+    query: String,
+    /// Index of the matching history entry
+    match_index: Option<usize>,
+}
+
+/// Lines shown in the console, a ring buffer keeping the most recent lines
+#[derive(Default)]
+pub(crate) struct Scrollback {
+    lines: VecDeque<String>,
+    /// Number of lines dropped to stay within the size limit
+    dropped: usize,
+    /// Number of lines ever pushed, including dropped and cleared lines
+    pushed: usize,
+}
+
+impl Scrollback {
+    /// Adds a line, dropping the oldest lines beyond `size`
+    pub(crate) fn push(&mut self, line: String, size: usize) {
+        self.lines.push_back(line);
+        self.pushed += 1;
+        while self.lines.len() > size {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.lines.clear();
+        self.dropped = 0;
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &String> {
+        self.lines.iter()
+    }
+}
+
+/// State of the scrollback search, opened with Ctrl+F
+#[derive(Default)]
+pub(crate) struct ScrollbackSearch {
+    query: String,
+    /// Interpret the query as a regular expression, otherwise it is matched ignoring case
+    regex: bool,
+    /// Hide the lines without a match
+    only_matching: bool,
+    /// Index of the selected line among the matching lines
+    current: usize,
+    /// Scrolls to the selected line on the next frame
+    scroll_to_current: bool,
+    matches: SearchMatches,
+}
+
+/// Matches of a [`ScrollbackSearch`], only the lines added to the scrollback are searched until the query changes
+#[derive(Default)]
+struct SearchMatches {
+    /// Query and regex flag the matches were found with
+    query: String,
+    regex: bool,
+    /// Compiled query, `None` if the query is empty or invalid
+    pattern: Option<Regex>,
+    error: Option<String>,
+    /// Number of lines pushed to the scrollback before the first line in `lines`
+    start: usize,
+    /// Ranges of the matches in each line
+    lines: VecDeque<Vec<Range<usize>>>,
+}
+
+impl ScrollbackSearch {
+    fn pattern(&self) -> Result<Regex, regex::Error> {
+        if self.regex {
+            Regex::new(&self.query)
+        } else {
+            RegexBuilder::new(&regex::escape(&self.query))
+                .case_insensitive(true)
+                .build()
+        }
+    }
+
+    /// Searches the lines added to the scrollback since the last update, or all lines if the query changed
+    fn update_matches(&mut self, scrollback: &Scrollback) {
+        if self.matches.query != self.query || self.matches.regex != self.regex {
+            let (pattern, error) = match self.pattern() {
+                _ if self.query.is_empty() => (None, None),
+                Ok(pattern) => (Some(pattern), None),
+                Err(_) => (None, Some("invalid regex".to_owned())),
+            };
+            self.matches = SearchMatches {
+                query: self.query.clone(),
+                regex: self.regex,
+                pattern,
+                error,
+                ..default()
+            };
+        }
+
+        // forget the lines dropped from the scrollback or cleared
+        let matches = &mut self.matches;
+        let first = scrollback.pushed - scrollback.lines.len();
+        let stale = first.saturating_sub(matches.start).min(matches.lines.len());
+        matches.lines.drain(..stale);
+        matches.start = (matches.start + stale).max(first);
+
+        let Some(pattern) = &matches.pattern else {
+            return;
+        };
+        let searched = matches.start + matches.lines.len() - first;
+        for line in scrollback.lines.iter().skip(searched) {
+            let text = parse_ansi_styled_str(line)
+                .into_iter()
+                .map(|(str, _)| str)
+                .collect::<String>();
+            matches.lines.push_back(
+                pattern
+                    .find_iter(&text)
+                    .map(|found| found.range())
+                    .filter(|range| !range.is_empty())
+                    .collect(),
+            );
+        }
+    }
+
+    /// Ranges of the matches in the text of each line, without ansi escape codes, as of the last update.
+    ///
+    /// Returns no lines if the query is empty.
+    fn matches(&self) -> Result<&VecDeque<Vec<Range<usize>>>, &str> {
+        match &self.matches.error {
+            Some(error) => Err(error),
+            None => Ok(&self.matches.lines),
+        }
+    }
 }
 
 impl Default for ConsoleState {
     fn default() -> Self {
         ConsoleState {
             buf: String::default(),
-            scrollback: Vec::new(),
+            scrollback: Scrollback::default(),
             history: VecDeque::from([String::new()]),
             history_index: 0,
             suggestion_index: None,
+            reverse_search: None,
+            scrollback_search: None,
+            cursor_to_end: false,
         }
     }
 }
@@ -400,8 +1045,20 @@ fn default_style(config: &ConsoleConfiguration) -> TextFormat {
     TextFormat::simple(FontId::monospace(14f32), config.foreground_color)
 }
 
-fn style_ansi_text(str: &str, config: &ConsoleConfiguration) -> LayoutJob {
+/// Background of the search matches in the scrollback
+const MATCH_COLOR: Color32 = Color32::from_rgba_premultiplied(100, 78, 2, 100);
+/// Background of the matches in the selected line
+const CURRENT_MATCH_COLOR: Color32 = Color32::from_rgb(160, 120, 4);
+
+/// Lays out a line with ansi escape codes, highlighting the `highlights` ranges of its text
+fn style_ansi_text(
+    str: &str,
+    config: &ConsoleConfiguration,
+    highlights: &[Range<usize>],
+    highlight_color: Color32,
+) -> LayoutJob {
     let mut layout_job = LayoutJob::default();
+    let mut offset = 0;
     for (str, overrides) in parse_ansi_styled_str(str).into_iter() {
         let mut current_style = default_style(config);
 
@@ -425,9 +1082,24 @@ fn style_ansi_text(str: &str, config: &ConsoleConfiguration) -> LayoutJob {
             }
         }
 
-        if !str.is_empty() {
-            layout_job.append(str, 0f32, current_style.clone());
+        // split the text where highlights start and end
+        let mut start = 0;
+        while start < str.len() {
+            let position = offset + start;
+            let (end, highlighted) = match highlights.iter().find(|range| range.end > position) {
+                Some(range) if range.start <= position => (range.end - offset, true),
+                Some(range) => (range.start - offset, false),
+                None => (str.len(), false),
+            };
+            let end = end.min(str.len());
+            let mut style = current_style.clone();
+            if highlighted {
+                style.background = highlight_color;
+            }
+            layout_job.append(&str[start..end], 0f32, style);
+            start = end;
         }
+        offset += str.len();
     }
     layout_job
 }
@@ -438,9 +1110,11 @@ pub(crate) fn recompute_predictions(
     state: &mut ConsoleState,
     cache: &mut ConsoleCache,
     suggestion_count: usize,
+    mode: CompletionMode,
 ) {
     if state.buf.is_empty() {
         cache.predictions_cache.clear();
+        cache.prediction_highlights.clear();
         cache.predictions_hash_key = None;
         cache.prediction_matches_buffer = false;
         state.suggestion_index = None;
@@ -459,18 +1133,50 @@ pub(crate) fn recompute_predictions(
         let words = Shlex::new(&state.buf).collect::<Vec<_>>();
         let query = words.join(" ");
 
-        let suggestions = match &cache.commands_trie {
-            Some(trie) if !query.is_empty() => trie
-                .predictive_search(query)
-                .into_iter()
-                .take(suggestion_count)
-                .collect(),
-            _ => vec![],
+        let suggestions: Vec<String> = match mode {
+            _ if query.is_empty() => vec![],
+            CompletionMode::Prefix => match &cache.commands_trie {
+                Some(trie) => trie
+                    .predictive_search(&query)
+                    .into_iter()
+                    .take(suggestion_count)
+                    .map(|s| String::from_utf8(s).unwrap_or_default())
+                    .collect(),
+                None => vec![],
+            },
+            CompletionMode::Fuzzy => {
+                let mut matches = cache
+                    .completions
+                    .iter()
+                    .filter_map(|completion| {
+                        fuzzy_match(&query, completion).map(|m| (m.score, completion))
+                    })
+                    .collect::<Vec<_>>();
+                matches.sort_by(|(a_score, a), (b_score, b)| {
+                    b_score
+                        .cmp(a_score)
+                        .then(a.len().cmp(&b.len()))
+                        .then(a.cmp(b))
+                });
+                matches
+                    .into_iter()
+                    .take(suggestion_count)
+                    .map(|(_, completion)| completion.clone())
+                    .collect()
+            }
         };
-        cache.predictions_cache = suggestions
-            .into_iter()
-            .map(|s| String::from_utf8(s).unwrap_or_default())
+        let mut predictions = cache.dynamic_predictions.clone();
+        predictions.extend(suggestions);
+        predictions.truncate(suggestion_count);
+        cache.prediction_highlights = predictions
+            .iter()
+            .map(|prediction| {
+                fuzzy_match(&query, prediction)
+                    .map(|m| m.indices)
+                    .unwrap_or_default()
+            })
             .collect();
+        cache.predictions_cache = predictions;
 
         cache.predictions_hash_key = Some(hash);
         state.suggestion_index = None;
@@ -490,7 +1196,7 @@ pub(crate) fn console_ui(
     mut cache: ResMut<ConsoleCache>,
     mut keyboard_input_events: EventReader<KeyboardInput>,
     mut state: ResMut<ConsoleState>,
-    command_entered: EventWriter<ConsoleCommandEntered>,
+    mut queue: ResMut<CommandQueue>,
     mut console_open: ResMut<ConsoleOpen>,
 ) {
     let keyboard_input_events = keyboard_input_events.read().collect::<Vec<_>>();
@@ -514,7 +1220,12 @@ pub(crate) fn console_ui(
 
     if console_open.open {
         // Recompute predictions if the buffer changed
-        recompute_predictions(&mut state, &mut cache, config.num_suggestions);
+        recompute_predictions(
+            &mut state,
+            &mut cache,
+            config.num_suggestions,
+            config.completion_mode,
+        );
 
         egui::Window::new(&config.title_name)
             .collapsible(config.collapsible)
@@ -533,7 +1244,42 @@ pub(crate) fn console_ui(
 
                 ui.vertical(|ui| {
                     const WRITE_AREA_HEIGHT: f32 = 30.0;
-                    let scroll_height = ui.available_height() - WRITE_AREA_HEIGHT;
+                    const PROGRESS_BAR_HEIGHT: f32 = 24.0;
+                    let tasks = queue
+                        .tasks
+                        .iter()
+                        .map(CommandTask::progress)
+                        .collect::<Vec<_>>();
+                    let scroll_height = ui.available_height()
+                        - WRITE_AREA_HEIGHT
+                        - PROGRESS_BAR_HEIGHT * tasks.len() as f32;
+
+                    // Matches of the scrollback search, by line
+                    let ConsoleState {
+                        scrollback,
+                        scrollback_search,
+                        ..
+                    } = &mut *state;
+                    if let Some(search) = scrollback_search {
+                        search.update_matches(scrollback);
+                    }
+                    let search = state.scrollback_search.as_ref();
+                    let search_result = search.map(ScrollbackSearch::matches);
+                    let search_error = search_result.and_then(Result::err).map(str::to_owned);
+                    let no_matches = VecDeque::new();
+                    let highlights = match search_result {
+                        Some(Ok(highlights)) => highlights,
+                        _ => &no_matches,
+                    };
+                    let matching_lines = (0..highlights.len())
+                        .filter(|&index| !highlights[index].is_empty())
+                        .collect::<Vec<_>>();
+                    let current_line =
+                        search.and_then(|search| matching_lines.get(search.current).copied());
+                    let only_matching =
+                        search.is_some_and(|search| search.only_matching) && !highlights.is_empty();
+                    let scroll_to_current = search.is_some_and(|search| search.scroll_to_current);
+
                     // Scroll area
                     ScrollArea::vertical()
                         .auto_shrink([false, false])
@@ -541,8 +1287,33 @@ pub(crate) fn console_ui(
                         .max_height(scroll_height)
                         .show(ui, |ui| {
                             ui.vertical(|ui| {
-                                for line in &state.scrollback {
-                                    ui.label(style_ansi_text(line, &config));
+                                if state.scrollback.dropped > 0 {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "... {} older lines dropped",
+                                            state.scrollback.dropped
+                                        ))
+                                        .monospace()
+                                        .weak(),
+                                    );
+                                }
+                                for (index, line) in state.scrollback.iter().enumerate() {
+                                    let ranges =
+                                        highlights.get(index).map_or(&[][..], Vec::as_slice);
+                                    if only_matching && ranges.is_empty() {
+                                        continue;
+                                    }
+                                    let is_current = current_line == Some(index);
+                                    let color = if is_current {
+                                        CURRENT_MATCH_COLOR
+                                    } else {
+                                        MATCH_COLOR
+                                    };
+                                    let response =
+                                        ui.label(style_ansi_text(line, &config, ranges, color));
+                                    if is_current && scroll_to_current {
+                                        response.scroll_to_me(Some(Align::Center));
+                                    }
                                 }
                             });
 
@@ -552,12 +1323,23 @@ pub(crate) fn console_ui(
                             }
                         });
 
+                    // Progress of running tasks, animated until they report progress
+                    for (command_name, progress) in tasks {
+                        ui.add(
+                            egui::ProgressBar::new(progress.unwrap_or(0.0))
+                                .text(format!("{command_name} - Ctrl+C to cancel"))
+                                .animate(progress.is_none()),
+                        );
+                    }
+
                     // Separator
                     ui.separator();
 
-                    // Clear line on ctrl+c
+                    // Clear line and cancel running command on ctrl+c
                     if ui.input(|i| i.modifiers.ctrl & i.key_pressed(egui::Key::C)) {
+                        queue.cancel();
                         state.buf.clear();
+                        state.reverse_search = None;
                         return;
                     }
 
@@ -567,6 +1349,45 @@ pub(crate) fn console_ui(
                         return;
                     }
 
+                    // Search history on ctrl+r, repeated presses cycle through older matches
+                    if ui.input(|i| i.modifiers.ctrl & i.key_pressed(egui::Key::R)) {
+                        let state = &mut *state;
+                        match &mut state.reverse_search {
+                            Some(search) => {
+                                let start = search.match_index.map_or(1, |index| index + 1);
+                                if let Some(index) =
+                                    search_history(&state.history, &search.query, start)
+                                {
+                                    search.match_index = Some(index);
+                                }
+                            }
+                            None => {
+                                state.reverse_search = Some(ReverseSearch::default());
+                                state.scrollback_search = None;
+                            }
+                        }
+                    }
+
+                    if state.reverse_search.is_some() {
+                        reverse_search_ui(ui, &mut state);
+                        return;
+                    }
+
+                    // Search the scrollback on ctrl+f
+                    if ui.input(|i| i.modifiers.ctrl & i.key_pressed(egui::Key::F)) {
+                        state.scrollback_search.get_or_insert_with(default);
+                    }
+
+                    if state.scrollback_search.is_some() {
+                        scrollback_search_ui(
+                            ui,
+                            &mut state.scrollback_search,
+                            matching_lines.len(),
+                            search_error,
+                        );
+                        return;
+                    }
+
                     // Input
                     let text_edit = TextEdit::singleline(&mut state.buf)
                         .desired_width(f32::INFINITY)
@@ -575,6 +1396,10 @@ pub(crate) fn console_ui(
 
                     let text_edit_response = ui.add(text_edit);
 
+                    if mem::take(&mut state.cursor_to_end) {
+                        set_cursor_pos(ui.ctx(), text_edit_response.id, state.buf.len());
+                    }
+
                     // show a few suggestions
                     if text_edit_response.has_focus()
                         && !state.buf.is_empty()
@@ -603,7 +1428,14 @@ pub(crate) fn console_ui(
                                     style.background = Color32::from_black_alpha(128);
                                 }
 
-                                layout_job.append(suggestion, 0.0, style);
+                                let highlights = &cache.prediction_highlights[i];
+                                for (index, c) in suggestion.chars().enumerate() {
+                                    let mut style = style.clone();
+                                    if highlights.contains(&index) {
+                                        style.color = Color32::from_rgb(255, 199, 6);
+                                    }
+                                    layout_job.append(c.encode_utf8(&mut [0; 4]), 0.0, style);
+                                }
                                 ui.label(layout_job);
                             }
                         });
@@ -613,7 +1445,7 @@ pub(crate) fn console_ui(
                         config,
                         &cache,
                         &mut state,
-                        command_entered,
+                        &mut queue,
                         ui,
                         &text_edit_response,
                     );
@@ -665,11 +1497,135 @@ pub(crate) fn console_ui(
     }
 }
 
+/// Shows the reverse search prompt in place of the input,
+/// enter puts the matching command in the input and escape cancels the search
+fn reverse_search_ui(ui: &mut egui::Ui, state: &mut ConsoleState) {
+    let ConsoleState {
+        buf,
+        history,
+        reverse_search,
+        cursor_to_end,
+        ..
+    } = state;
+    let Some(search) = reverse_search else {
+        return;
+    };
+
+    let response = ui
+        .horizontal(|ui| {
+            ui.label(egui::RichText::new("(reverse-i-search)`").monospace());
+            let response = ui.add(
+                TextEdit::singleline(&mut search.query)
+                    .desired_width(150.0)
+                    .lock_focus(true)
+                    .font(egui::TextStyle::Monospace),
+            );
+            let matched = search
+                .match_index
+                .and_then(|index| history.get(index))
+                .map(String::as_str)
+                .unwrap_or_default();
+            ui.label(egui::RichText::new(format!("': {matched}")).monospace());
+            response
+        })
+        .inner;
+
+    if response.changed() {
+        search.match_index = search_history(history, &search.query, 1);
+    }
+
+    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        *reverse_search = None;
+    } else if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+        if let Some(matched) = search.match_index.and_then(|index| history.get(index)) {
+            *buf = matched.clone();
+            *cursor_to_end = true;
+        }
+        *reverse_search = None;
+    } else {
+        ui.memory_mut(|m| m.request_focus(response.id));
+    }
+}
+
+/// Shows the scrollback search bar in place of the input,
+/// enter and shift+enter select the next and previous matching line and escape closes the search
+fn scrollback_search_ui(
+    ui: &mut egui::Ui,
+    scrollback_search: &mut Option<ScrollbackSearch>,
+    match_count: usize,
+    error: Option<String>,
+) {
+    let Some(search) = scrollback_search else {
+        return;
+    };
+    if search.current >= match_count {
+        search.current = 0;
+    }
+
+    let response = ui
+        .horizontal(|ui| {
+            ui.label(egui::RichText::new("search:").monospace());
+            let response = ui.add(
+                TextEdit::singleline(&mut search.query)
+                    .desired_width(200.0)
+                    .lock_focus(true)
+                    .font(egui::TextStyle::Monospace),
+            );
+            ui.checkbox(&mut search.regex, "regex");
+            ui.checkbox(&mut search.only_matching, "only matching");
+            let status = match error {
+                Some(error) => error,
+                None if search.query.is_empty() => String::new(),
+                None if match_count == 0 => "no matches".into(),
+                None => format!("{}/{match_count}", search.current + 1),
+            };
+            ui.label(egui::RichText::new(status).monospace());
+            response
+        })
+        .inner;
+
+    if response.changed() {
+        search.current = 0;
+        search.scroll_to_current = true;
+    } else {
+        search.scroll_to_current = false;
+    }
+
+    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        *scrollback_search = None;
+        return;
+    }
+    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && match_count > 0 {
+        search.current = if ui.input(|i| i.modifiers.shift) {
+            (search.current + match_count - 1) % match_count
+        } else {
+            (search.current + 1) % match_count
+        };
+        search.scroll_to_current = true;
+    }
+    ui.memory_mut(|m| m.request_focus(response.id));
+}
+
+/// Finds the most recent history entry containing `query`, starting at `start` and searching towards older entries.
+///
+/// The first entry is skipped as it holds the line being edited.
+fn search_history(history: &VecDeque<String>, query: &str, start: usize) -> Option<usize> {
+    if query.is_empty() {
+        return None;
+    }
+    history
+        .iter()
+        .enumerate()
+        .skip(start.max(1))
+        .find(|(_, command)| command.contains(query))
+        .map(|(index, _)| index)
+}
+
 fn handle_enter(
     config: Res<'_, ConsoleConfiguration>,
     cache: &ResMut<'_, ConsoleCache>,
     state: &mut ResMut<'_, ConsoleState>,
-    mut command_entered: EventWriter<'_, ConsoleCommandEntered>,
+    queue: &mut CommandQueue,
     ui: &mut egui::Ui,
     text_edit_response: &egui::Response,
 ) {
@@ -687,49 +1643,109 @@ fn handle_enter(
         }
 
         if state.buf.trim().is_empty() {
-            state.scrollback.push(String::new());
+            state.scrollback.push(String::new(), config.scrollback_size);
         } else {
             let msg = format!("{}{}", config.symbol, state.buf);
-            state.scrollback.push(msg);
+            state.scrollback.push(msg, config.scrollback_size);
             let cmd_string = state.buf.clone();
-            state.history.insert(1, cmd_string);
-            if state.history.len() > config.history_size + 1 {
-                state.history.pop_back();
-            }
+            record_history(&mut state.history, cmd_string, &config);
             state.history_index = 0;
 
-            let mut args = Shlex::new(&state.buf).collect::<Vec<_>>();
+            if let Err(err) = queue.push_line(&state.buf, CommandSource::Console) {
+                state
+                    .scrollback
+                    .push(format!("error: {err}"), config.scrollback_size);
+            }
 
-            if !args.is_empty() {
-                let command_name = args.remove(0);
-                debug!("Command entered: `{command_name}`, with args: `{args:?}`");
+            state.buf.clear();
+        }
+    }
+}
 
-                let command = config.commands.get(command_name.as_str());
+/// Adds a command to the front of the history, removing older duplicates and trimming it to `history_size`.
+///
+/// The first entry is reserved for the line being edited.
+/// Returns `false` if older commands were removed.
+fn add_to_history(history: &mut VecDeque<String>, command: String, history_size: usize) -> bool {
+    let mut kept = true;
+    if let Some(index) = history.iter().skip(1).position(|item| item == &command) {
+        history.remove(index + 1);
+        kept = false;
+    }
+    history.insert(1, command);
+    if history.len() > history_size + 1 {
+        history.truncate(history_size + 1);
+        kept = false;
+    }
+    kept
+}
 
-                if command.is_some() {
-                    command_entered.write(ConsoleCommandEntered { command_name, args });
-                } else {
-                    debug!(
-                        "Command not recognized, recognized commands: `{:?}`",
-                        config.commands.keys().collect::<Vec<_>>()
-                    );
+/// Adds an entered command to the history and to [`ConsoleConfiguration::history_file`],
+/// the file is only rewritten when older commands were removed from the history
+fn record_history(history: &mut VecDeque<String>, command: String, config: &ConsoleConfiguration) {
+    let kept = add_to_history(history, command, config.history_size);
+    match &config.history_file {
+        Some(path) if kept => append_history(path, &history[1]),
+        Some(path) => save_history(path, history),
+        None => {}
+    }
+}
 
-                    state.scrollback.push("error: Invalid command".into());
-                }
-            }
+/// Appends a command to the history file
+fn append_history(path: &Path, command: &str) {
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{command}"));
+    if let Err(err) = result {
+        warn!("Failed to write history to '{}': {err}", path.display());
+    }
+}
 
-            state.buf.clear();
+/// Writes the history to a file, oldest command first
+fn save_history(path: &Path, history: &VecDeque<String>) {
+    let mut contents = String::new();
+    for command in history.iter().skip(1).rev() {
+        contents.push_str(command);
+        contents.push('\n');
+    }
+    if let Err(err) = std::fs::write(path, contents) {
+        warn!("Failed to write history to '{}': {err}", path.display());
+    }
+}
+
+/// Loads the history saved in [`ConsoleConfiguration::history_file`]
+pub(crate) fn load_history(config: Res<ConsoleConfiguration>, mut state: ResMut<ConsoleState>) {
+    let Some(path) = &config.history_file else {
+        return;
+    };
+    match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            for command in contents.lines().filter(|line| !line.trim().is_empty()) {
+                add_to_history(&mut state.history, command.to_owned(), config.history_size);
+            }
         }
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => warn!("Failed to read history from '{}': {err}", path.display()),
     }
 }
 
 pub(crate) fn receive_console_line(
+    config: Res<ConsoleConfiguration>,
     mut console_state: ResMut<ConsoleState>,
+    queue: Res<CommandQueue>,
     mut events: EventReader<PrintConsoleLine>,
 ) {
     for event in events.read() {
         let event: &PrintConsoleLine = event;
-        console_state.scrollback.push(event.line.clone());
+        // piped output is only shown by the last command of the pipe
+        if queue.is_piped(event) {
+            continue;
+        }
+        console_state
+            .scrollback
+            .push(event.line.clone(), config.scrollback_size);
     }
 }
 
@@ -798,6 +1814,60 @@ mod tests {
     use bevy::input::ButtonState;
 
     use super::*;
+    use crate::testing::{note_command, test_app, MarkCommand, Marks, NoteCommand};
+    use crate::{CommandSource, ConsoleVar, ConsoleVars, Invocation, RunConsoleLine};
+
+    #[test]
+    fn test_add_to_history() {
+        let mut history = VecDeque::from([String::new()]);
+        for command in ["a", "b", "c", "a"] {
+            add_to_history(&mut history, command.into(), 3);
+        }
+        assert_eq!(history, ["", "a", "c", "b"]);
+
+        add_to_history(&mut history, "d".into(), 3);
+        assert_eq!(history, ["", "d", "a", "c"]);
+    }
+
+    #[test]
+    fn test_history_file() {
+        let path =
+            std::env::temp_dir().join(format!("bevy_console_history_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = ConsoleConfiguration {
+            history_size: 3,
+            history_file: Some(path.clone()),
+            ..default()
+        };
+        let mut history = VecDeque::from([String::new()]);
+        let mut record = |command: &str| {
+            record_history(&mut history, command.to_owned(), &config);
+            std::fs::read_to_string(&path).unwrap()
+        };
+
+        assert_eq!(record("god"), "god\n");
+        assert_eq!(record("noclip"), "god\nnoclip\n");
+        // duplicates and trimmed commands are removed from the file
+        assert_eq!(record("god"), "noclip\ngod\n");
+        assert_eq!(record("spawn"), "noclip\ngod\nspawn\n");
+        assert_eq!(record("kill"), "god\nspawn\nkill\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_search_history() {
+        let history = VecDeque::from([
+            "spawn".to_string(),
+            "spawn boss".into(),
+            "god".into(),
+            "spawn enemy".into(),
+        ]);
+        assert_eq!(search_history(&history, "spawn", 1), Some(1));
+        assert_eq!(search_history(&history, "spawn", 2), Some(3));
+        assert_eq!(search_history(&history, "spawn", 4), None);
+        assert_eq!(search_history(&history, "go", 0), Some(2));
+        assert_eq!(search_history(&history, "", 1), None);
+    }
 
     #[test]
     fn test_console_key_pressed_scan_code() {
@@ -883,4 +1953,178 @@ mod tests {
         let result = console_key_pressed(&input, &config);
         assert!(!result);
     }
+
+    #[test]
+    fn test_scrollback_search() {
+        let mut scrollback = Scrollback::default();
+        for line in [
+            "WARN missing asset",
+            "\x1b[33mwarn\x1b[0m: slow frame",
+            "all good",
+        ] {
+            scrollback.push(line.to_owned(), 10);
+        }
+        let find = |search: &mut ScrollbackSearch, scrollback: &Scrollback| {
+            search.update_matches(scrollback);
+            search
+                .matches()
+                .map(|matches| matches.iter().cloned().collect::<Vec<_>>())
+                .map_err(str::to_owned)
+        };
+        let mut search = ScrollbackSearch {
+            query: "warn".into(),
+            ..default()
+        };
+        assert_eq!(
+            find(&mut search, &scrollback),
+            Ok(vec![vec![0..4], vec![0..4], vec![]])
+        );
+
+        // only new lines are searched, dropped lines are forgotten
+        scrollback.push("warn again".to_owned(), 3);
+        assert_eq!(
+            find(&mut search, &scrollback),
+            Ok(vec![vec![0..4], vec![], vec![0..4]])
+        );
+        scrollback.clear();
+        scrollback.push("no warnings".to_owned(), 3);
+        assert_eq!(find(&mut search, &scrollback), Ok(vec![vec![3..7]]));
+
+        search.regex = true;
+        search.query = r"\w+ (asset|frame)$".into();
+        scrollback.push("WARN missing asset".to_owned(), 3);
+        assert_eq!(
+            find(&mut search, &scrollback),
+            Ok(vec![vec![], vec![5..18]])
+        );
+        search.query = "(".into();
+        assert!(find(&mut search, &scrollback).is_err());
+        search.query.clear();
+        assert_eq!(find(&mut search, &scrollback), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_style_highlights() {
+        let config = ConsoleConfiguration::default();
+        let job = style_ansi_text(
+            "\x1b[33mwarn\x1b[0m: slow frame",
+            &config,
+            &[2..8, 11..16],
+            MATCH_COLOR,
+        );
+        let sections = job
+            .sections
+            .iter()
+            .map(|section| {
+                (
+                    &job.text[section.byte_range.clone()],
+                    section.format.background == MATCH_COLOR,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            vec![
+                ("wa", false),
+                ("rn", true),
+                (": sl", true),
+                ("ow ", false),
+                ("frame", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scrollback_size() {
+        let mut scrollback = Scrollback::default();
+        for line in ["a", "b", "c", "d"] {
+            scrollback.push(line.to_owned(), 2);
+        }
+        assert_eq!(scrollback.iter().collect::<Vec<_>>(), ["c", "d"]);
+        assert_eq!(scrollback.dropped, 2);
+
+        scrollback.clear();
+        scrollback.push("e".to_owned(), 2);
+        assert_eq!(scrollback.iter().collect::<Vec<_>>(), ["e"]);
+        assert_eq!(scrollback.dropped, 0);
+    }
+
+    #[test]
+    fn test_runtime_commands() {
+        let mut app = test_app();
+        // let the startup registration of the commands run first
+        app.update();
+        app.register_console_command::<NoteCommand, _>(note_command)
+            .unregister_console_command("mark");
+        let output = app
+            .run_console_line("note a; mark b || note c; note d")
+            .unwrap();
+        assert_eq!(output.result, Ok(None));
+        assert_eq!(
+            app.world().resource::<Marks>().0,
+            vec!["note a", "note c", "note d"]
+        );
+
+        app.unregister_console_command("note")
+            .insert_resource(Marks::default());
+        assert!(!app
+            .world()
+            .resource::<ConsoleConfiguration>()
+            .commands
+            .contains_key("note"));
+        assert!(app
+            .run_console_line("note a || fail")
+            .unwrap()
+            .result
+            .is_err());
+        assert!(app.world().resource::<Marks>().0.is_empty());
+    }
+
+    #[test]
+    fn test_invocation_source() {
+        let mut app = test_app();
+        app.world_mut()
+            .resource_mut::<ConsoleVars>()
+            .register(ConsoleVar::new("bots", 0));
+        let mut queue = app.world_mut().resource_mut::<CommandQueue>();
+        let console = queue.push_line("bots", CommandSource::Console).unwrap();
+        let remote = queue.push_line("bots", CommandSource::Remote(7)).unwrap();
+        assert_ne!(console, remote);
+        app.update();
+
+        let lines = app.world().resource::<Events<PrintConsoleLine>>();
+        let invocations = lines
+            .iter_current_update_events()
+            .map(|line| line.invocation.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            invocations,
+            vec![
+                Invocation {
+                    id: console,
+                    source: CommandSource::Console
+                },
+                Invocation {
+                    id: remote,
+                    source: CommandSource::Remote(7)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replaced_command() {
+        let mut app = test_app();
+        app.update();
+        app.register_console_command::<MarkCommand, _>(
+            |mut mark: ConsoleCommand<MarkCommand>, mut marks: ResMut<Marks>| {
+                if let Some(Ok(MarkCommand { name })) = mark.take() {
+                    marks.0.push(format!("replaced {name}"));
+                }
+            },
+        );
+        app.run_console_line("mark a").unwrap();
+        // the system added with `add_console_command` doesn't run anymore
+        assert_eq!(app.world().resource::<Marks>().0, vec!["replaced a"]);
+    }
 }
@@ -0,0 +1,258 @@
+use std::any::type_name;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use bevy::ecs::event::EventCursor;
+use bevy::prelude::*;
+use clap::{Arg, ArgMatches};
+
+use crate::console::insert_command_system;
+use crate::result::{result_lines, CommandError, CommandResult, IntoCommandValue};
+use crate::{ConsoleCommandCompleted, ConsoleCommandEntered, PrintConsoleLine};
+
+/// Functions which can be registered as console commands with
+/// [`AddConsoleCommand::add_console_fn`](crate::AddConsoleCommand::add_console_fn).
+///
+/// Implemented for functions and closures taking up to 8 arguments parsed with [`FromStr`],
+/// followed by `&mut World`, and returning a [`ConsoleFnResult`].
+pub trait ConsoleFn<Marker>: Send + Sync + 'static {
+    /// Positional arguments of the command, one for each argument of the function
+    fn args() -> Vec<Arg>;
+
+    /// Parses the arguments and calls the function
+    fn run(&self, matches: &ArgMatches, world: &mut World) -> CommandResult;
+}
+
+/// Return values of functions registered as console commands,
+/// a value implementing [`IntoCommandValue`] or a `Result` of one.
+pub trait ConsoleFnResult {
+    /// Converts the value into the result of the command
+    fn into_result(self) -> CommandResult;
+}
+
+impl<T: IntoCommandValue> ConsoleFnResult for T {
+    fn into_result(self) -> CommandResult {
+        Ok(self.into_command_value())
+    }
+}
+
+impl<T: IntoCommandValue, E: Into<CommandError>> ConsoleFnResult for Result<T, E> {
+    fn into_result(self) -> CommandResult {
+        self.map(IntoCommandValue::into_command_value)
+            .map_err(Into::into)
+    }
+}
+
+fn arg<T>(id: &'static str) -> Arg {
+    // `alloc::string::String` is shown as `<String>`
+    let value_name = type_name::<T>().rsplit("::").next().unwrap_or(id);
+    Arg::new(id).required(true).value_name(value_name)
+}
+
+fn parse_arg<T: FromStr>(matches: &ArgMatches, id: &str) -> Result<T, CommandError>
+where
+    T::Err: Display,
+{
+    let value = matches
+        .get_one::<String>(id)
+        .ok_or_else(|| CommandError::new(format!("missing argument <{id}>")).with_code(2))?;
+    value.parse().map_err(|err| {
+        CommandError::new(format!(
+            "invalid value '{value}' for <{}>: {err}",
+            type_name::<T>().rsplit("::").next().unwrap_or(id)
+        ))
+        .with_code(2)
+    })
+}
+
+macro_rules! impl_console_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> ConsoleFn<fn($($arg,)*) -> R> for F
+        where
+            F: Fn($($arg,)* &mut World) -> R + Send + Sync + 'static,
+            R: ConsoleFnResult,
+            $($arg: FromStr + 'static, $arg::Err: Display,)*
+        {
+            fn args() -> Vec<Arg> {
+                vec![$(arg::<$arg>(stringify!($arg)),)*]
+            }
+
+            #[allow(non_snake_case, unused_variables)]
+            fn run(&self, matches: &ArgMatches, world: &mut World) -> CommandResult {
+                $(let $arg = parse_arg::<$arg>(matches, stringify!($arg))?;)*
+                (self)($($arg,)* world).into_result()
+            }
+        }
+    };
+}
+
+impl_console_fn!();
+impl_console_fn!(A1);
+impl_console_fn!(A1, A2);
+impl_console_fn!(A1, A2, A3);
+impl_console_fn!(A1, A2, A3, A4);
+impl_console_fn!(A1, A2, A3, A4, A5);
+impl_console_fn!(A1, A2, A3, A4, A5, A6);
+impl_console_fn!(A1, A2, A3, A4, A5, A6, A7);
+impl_console_fn!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+/// Handler of a command run with exclusive world access
+pub(crate) type ExclusiveHandler =
+    Box<dyn Fn(&ArgMatches, &mut World) -> CommandResult + Send + Sync>;
+
+/// Registers `command` with a one-shot system calling `handler` whenever the command is entered
+pub(crate) fn register_exclusive_command(
+    world: &mut World,
+    name: &'static str,
+    command: clap::Command,
+    handler: ExclusiveHandler,
+) {
+    let clap_command = command.clone();
+    let id = world.register_system(
+        move |world: &mut World, mut entered: Local<EventCursor<ConsoleCommandEntered>>| {
+            let Some((args, invocation)) = entered
+                .read(world.resource::<Events<ConsoleCommandEntered>>())
+                .filter(|entered| entered.command_name == name)
+                .last()
+                .map(|entered| (entered.args.clone(), entered.invocation))
+            else {
+                return;
+            };
+
+            let result = match clap_command.clone().try_get_matches_from(args) {
+                Ok(matches) => {
+                    let result = handler(&matches, world);
+                    // commands without a value complete silently
+                    if result != Ok(None) {
+                        for line in result_lines(&result) {
+                            world.send_event(line.with_invocation(invocation));
+                        }
+                    }
+                    result
+                }
+                Err(err) => {
+                    let line = PrintConsoleLine::new(err.to_string()).with_invocation(invocation);
+                    // `--help` is reported as an error by clap, but is not a failure
+                    if !err.use_stderr() {
+                        world.send_event(line);
+                        return;
+                    }
+                    world.send_event(line.as_error());
+                    Err(CommandError::new(err.to_string()).with_code(2))
+                }
+            };
+            world
+                .send_event(ConsoleCommandCompleted::new(name, result).with_invocation(invocation));
+        },
+    );
+    insert_command_system(world, name, command, id);
+}
+
+/// Builds the `clap` command of a function registered as a console command
+pub(crate) fn fn_command<F: ConsoleFn<Marker>, Marker>(
+    name: &'static str,
+    about: &'static str,
+    _function: &F,
+) -> clap::Command {
+    clap::Command::new(name)
+        .about(about)
+        .no_binary_name(true)
+        .args(F::args())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_app, Marks, NoteCommand};
+    use crate::{AddConsoleCommand, CommandValue, RunConsoleLine};
+
+    fn heal(_amount: u32, _target: String, _world: &mut World) {}
+
+    #[test]
+    fn test_fn_command() {
+        let mut command = fn_command("heal", "Heal the player", &heal);
+        assert_eq!(
+            command.render_usage().to_string(),
+            "Usage: heal <u32> <String>"
+        );
+
+        let mut world = World::new();
+        let matches = command
+            .clone()
+            .try_get_matches_from(["x", "player"])
+            .unwrap();
+        assert_eq!(
+            heal.run(&matches, &mut world),
+            Err(
+                CommandError::new("invalid value 'x' for <u32>: invalid digit found in string")
+                    .with_code(2)
+            )
+        );
+        let matches = command.try_get_matches_from(["10", "player"]).unwrap();
+        assert_eq!(heal.run(&matches, &mut world), Ok(None));
+
+        let sum = |a: i64, b: i64, _: &mut World| a + b;
+        let matches = fn_command("sum", "Adds two numbers", &sum)
+            .try_get_matches_from(["1", "2"])
+            .unwrap();
+        assert_eq!(
+            sum.run(&matches, &mut world),
+            Ok(Some(CommandValue::Int(3)))
+        );
+    }
+
+    #[test]
+    fn test_console_fn() {
+        let mut app = test_app();
+        app.add_console_fn(
+            "repeat",
+            "Marks a name several times",
+            |name: String, times: u32, world: &mut World| {
+                if times == 0 {
+                    return Err("times must be positive");
+                }
+                let mut marks = world.resource_mut::<Marks>();
+                marks.0.extend((0..times).map(|_| name.clone()));
+                Ok(())
+            },
+        );
+        let output = app
+            .run_console_line("repeat a 2 && mark b; repeat c 0 || mark d; repeat e x || mark f")
+            .unwrap();
+        assert_eq!(output.result, Ok(None));
+        assert_eq!(
+            app.world().resource::<Marks>().0,
+            vec!["a", "a", "b", "d", "f"]
+        );
+    }
+
+    #[test]
+    fn test_exclusive_console_command() {
+        let mut app = test_app();
+        app.add_exclusive_console_command(|note: NoteCommand, world: &mut World| {
+            if note.name == "fail" {
+                return Err(format!("can't note '{}'", note.name));
+            }
+            world.spawn(Name::new(note.name));
+            world
+                .run_system_cached_with(
+                    |In(name): In<String>, mut marks: ResMut<Marks>| marks.0.push(name),
+                    "noted".to_string(),
+                )
+                .unwrap();
+            Ok(())
+        });
+        let output = app
+            .run_console_line("note a && note fail || mark b")
+            .unwrap();
+        assert_eq!(output.result, Ok(None));
+
+        let mut names = app.world_mut().query::<&Name>();
+        let names = names
+            .iter(app.world())
+            .map(Name::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a"]);
+        assert_eq!(app.world().resource::<Marks>().0, vec!["noted", "b"]);
+    }
+}
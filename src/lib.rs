@@ -4,27 +4,71 @@
 use bevy::prelude::*;
 pub use bevy_console_derive::ConsoleCommand;
 use bevy_egui::{EguiContextPass, EguiPlugin, EguiPreUpdateSet};
-use console::{block_keyboard_input, block_mouse_input, ConsoleCache};
+use console::{
+    block_keyboard_input, block_mouse_input, index_entered_commands, ConsoleCache,
+    ConsoleCommandSystems, EnteredCommands,
+};
+use std::collections::HashSet;
 use trie_rs::TrieBuilder;
 
+use crate::commands::alias::{
+    alias_command, unalias_command, AliasCommand, ConsoleAliases, UnaliasCommand,
+};
+use crate::commands::bind::{
+    bind_command, bindlist_command, run_key_bindings, unbind_command, BindCommand, BindListCommand,
+    ConsoleBindings, UnbindCommand,
+};
 use crate::commands::clear::{clear_command, ClearCommand};
+use crate::commands::echo::{echo_command, EchoCommand};
+use crate::commands::exec::{exec_command, ExecCommand};
 use crate::commands::exit::{exit_command, ExitCommand};
+use crate::commands::filters::{
+    count_command, grep_command, head_command, sort_command, tail_command, CountCommand,
+    GrepCommand, HeadCommand, SortCommand, TailCommand,
+};
 use crate::commands::help::{help_command, HelpCommand};
+use crate::commands::reset::{reset_command, ResetCommand};
+use crate::commands::set::{set_command, unset_command, SetCommand, UnsetCommand};
+use crate::commands::wait::{wait_command, WaitCommand};
+use crate::commands::writeconfig::{writeconfig_command, WriteConfigCommand};
+use crate::completion::{command_completions, update_dynamic_completions, ConsoleCompleters};
+pub use crate::completion::{AddConsoleCompleter, CompletionMode, ConsoleCompleter};
 pub use crate::console::{
-    AddConsoleCommand, Command, ConsoleCommand, ConsoleCommandEntered, ConsoleConfiguration,
-    ConsoleOpen, NamedCommand, PrintConsoleLine,
+    AddConsoleCommand, Command, CommandSource, ConsoleCommand, ConsoleCommandCompleted,
+    ConsoleCommandEntered, ConsoleConfiguration, ConsoleOpen, Invocation, InvocationId,
+    NamedCommand, PrintConsoleLine, RegisterConsoleCommand,
 };
+pub use crate::console_fn::{ConsoleFn, ConsoleFnResult};
 pub use crate::log::*;
+pub use crate::queue::CommandQueue;
+pub use crate::result::{CommandError, CommandResult, CommandValue, IntoCommandValue};
+pub use crate::runner::{CommandOutput, ConsoleRunner, RunConsoleLine};
+pub use crate::task::CommandTask;
+pub use crate::vars::{AddConsoleVar, ConsoleVar, ConsoleVarChanged, ConsoleVarValue, ConsoleVars};
 
-use crate::console::{console_ui, receive_console_line, ConsoleState};
+use crate::console::{console_ui, load_history, receive_console_line, ConsoleState};
+use crate::queue::{collect_piped_output, execute_queue, queue_startup_commands};
+use crate::runner::capture_output;
+use crate::task::{update_tasks, TaskStarted};
+use crate::vars::{load_config_file, send_console_var_changed};
 pub use clap;
 
 // mod color;
 mod color;
 mod commands;
+mod completion;
 mod console;
+mod console_fn;
 mod log;
 mod macros;
+mod parser;
+mod queue;
+mod result;
+mod runner;
+mod task;
+#[cfg(test)]
+mod testing;
+mod vars;
 /// Console plugin.
 pub struct ConsolePlugin;
 
@@ -53,32 +97,70 @@ fn have_commands(commands: EventReader<ConsoleCommandEntered>) -> bool {
 }
 
 /// builds the predictive search engine for completions
-fn init(config: Res<ConsoleConfiguration>, mut cache: ResMut<ConsoleCache>) {
-    let mut trie_builder = TrieBuilder::new();
-    for cmd in config.commands.keys() {
-        trie_builder.push(cmd);
+fn init(
+    config: Res<ConsoleConfiguration>,
+    aliases: Res<ConsoleAliases>,
+    vars: Res<ConsoleVars>,
+    mut cache: ResMut<ConsoleCache>,
+    mut shadowed_vars: Local<HashSet<String>>,
+) {
+    // commands are dispatched before variables, so the variable can't be used
+    for name in vars.names() {
+        if config.commands.contains_key(name) && shadowed_vars.insert(name.to_owned()) {
+            warn!("console variable '{name}' is shadowed by the command of the same name");
+        }
     }
 
-    for completions in &config.arg_completions {
-        trie_builder.push(completions.join(" "));
+    let mut completions = Vec::new();
+    for (name, command) in &config.commands {
+        completions.extend(command_completions(name, command));
     }
 
+    completions.extend(aliases.0.keys().cloned());
+    completions.extend(vars.names().map(str::to_owned));
+
+    for arg_completions in &config.arg_completions {
+        completions.push(arg_completions.join(" "));
+    }
+    completions.sort();
+    completions.dedup();
+
+    let mut trie_builder = TrieBuilder::new();
+    for completion in &completions {
+        trie_builder.push(completion);
+    }
     cache.commands_trie = Some(trie_builder.build());
+    cache.completions = completions;
+    // the buffer may match new completions
+    cache.predictions_hash_key = None;
 }
 
 impl Plugin for ConsolePlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<ConsoleConfiguration>()
-            .init_resource::<ConsoleState>()
-            .init_resource::<ConsoleOpen>()
-            .init_resource::<ConsoleCache>()
-            .add_event::<ConsoleCommandEntered>()
-            .add_event::<PrintConsoleLine>()
-            .add_console_command::<ClearCommand, _>(clear_command)
-            .add_console_command::<ExitCommand, _>(exit_command)
-            .add_console_command::<HelpCommand, _>(help_command)
+        add_command_systems(app);
+        app.init_resource::<ConsoleCache>()
+            .init_resource::<ConsoleCompleters>()
             // after per-command startup
-            .add_systems(Startup, init.after(ConsoleSet::Startup))
+            .add_systems(
+                Startup,
+                (
+                    init,
+                    load_history,
+                    load_config_file,
+                    queue_startup_commands.after(load_config_file),
+                )
+                    .after(ConsoleSet::Startup),
+            )
+            // commands, aliases and variables can be changed at runtime
+            .add_systems(
+                Update,
+                init.run_if(
+                    resource_changed::<ConsoleConfiguration>
+                        .or(resource_changed::<ConsoleAliases>)
+                        .or(resource_changed::<ConsoleVars>),
+                )
+                .in_set(ConsoleSet::PostCommands),
+            )
             .add_systems(
                 PreUpdate,
                 (block_mouse_input, block_keyboard_input)
@@ -87,19 +169,9 @@ impl Plugin for ConsolePlugin {
             )
             .add_systems(
                 EguiContextPass,
-                (
-                    console_ui.in_set(ConsoleSet::ConsoleUI),
-                    receive_console_line.in_set(ConsoleSet::PostCommands),
-                ),
-            )
-            .configure_sets(
-                EguiContextPass,
-                (
-                    ConsoleSet::Commands
-                        .after(ConsoleSet::ConsoleUI)
-                        .run_if(have_commands),
-                    ConsoleSet::PostCommands.after(ConsoleSet::Commands),
-                ),
+                (update_dynamic_completions, console_ui)
+                    .chain()
+                    .in_set(ConsoleSet::ConsoleUI),
             );
 
         // Don't initialize an egui plugin if one already exists.
@@ -111,3 +183,78 @@ impl Plugin for ConsolePlugin {
         }
     }
 }
+
+/// Adds the resources, built-in commands and systems executing console lines, everything but the console window
+fn add_command_systems(app: &mut App) {
+    app.init_resource::<ConsoleConfiguration>()
+        .init_resource::<ConsoleState>()
+        .init_resource::<ConsoleOpen>()
+        .init_resource::<CommandQueue>()
+        .init_resource::<ConsoleAliases>()
+        .init_resource::<ConsoleBindings>()
+        .init_resource::<ConsoleVars>()
+        .init_resource::<ConsoleCommandSystems>()
+        .init_resource::<EnteredCommands>()
+        .add_event::<ConsoleCommandEntered>()
+        .add_event::<ConsoleCommandCompleted>()
+        .add_event::<PrintConsoleLine>()
+        .add_event::<ConsoleVarChanged>()
+        .add_event::<TaskStarted>()
+        .add_console_command::<ClearCommand, _>(clear_command)
+        .add_console_command::<ExitCommand, _>(exit_command)
+        .add_console_command::<HelpCommand, _>(help_command)
+        .add_console_command::<AliasCommand, _>(alias_command)
+        .add_console_command::<UnaliasCommand, _>(unalias_command)
+        .add_console_command::<ExecCommand, _>(exec_command)
+        .add_console_command::<ResetCommand, _>(reset_command)
+        .add_console_command::<WriteConfigCommand, _>(writeconfig_command)
+        .add_console_command::<WaitCommand, _>(wait_command)
+        .add_console_command::<BindCommand, _>(bind_command)
+        .add_console_command::<UnbindCommand, _>(unbind_command)
+        .add_console_command::<BindListCommand, _>(bindlist_command)
+        .add_console_command::<SetCommand, _>(set_command)
+        .add_console_command::<UnsetCommand, _>(unset_command)
+        .add_console_command::<EchoCommand, _>(echo_command)
+        .add_console_command::<GrepCommand, _>(grep_command)
+        .add_console_command::<HeadCommand, _>(head_command)
+        .add_console_command::<TailCommand, _>(tail_command)
+        .add_console_command::<CountCommand, _>(count_command)
+        .add_console_command::<SortCommand, _>(sort_command)
+        .add_systems(
+            Update,
+            (
+                send_console_var_changed,
+                (update_tasks, capture_output).chain(),
+                collect_piped_output.after(update_tasks),
+            )
+                .in_set(ConsoleSet::PostCommands),
+        )
+        .add_systems(Update, execute_queue.in_set(ConsoleSet::ConsoleUI))
+        .add_systems(Update, run_key_bindings.before(ConsoleSet::ConsoleUI))
+        .add_systems(
+            Update,
+            index_entered_commands
+                .after(ConsoleSet::ConsoleUI)
+                .before(ConsoleSet::Commands),
+        )
+        .add_systems(
+            EguiContextPass,
+            receive_console_line.in_set(ConsoleSet::PostCommands),
+        )
+        .configure_sets(
+            Update,
+            (
+                ConsoleSet::Commands.after(ConsoleSet::ConsoleUI),
+                ConsoleSet::PostCommands.after(ConsoleSet::Commands),
+            ),
+        )
+        .configure_sets(
+            EguiContextPass,
+            (
+                ConsoleSet::Commands
+                    .after(ConsoleSet::ConsoleUI)
+                    .run_if(have_commands),
+                ConsoleSet::PostCommands.after(ConsoleSet::Commands),
+            ),
+        );
+}
@@ -0,0 +1,308 @@
+/// How a command is connected to the command before it on a console line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Connector {
+    /// `;` or the start of a line, always runs
+    Then,
+    /// `&&`, runs only if the previous command succeeded
+    And,
+    /// `||`, runs only if the previous command failed
+    Or,
+    /// `|`, runs with the output of the previous command as input
+    Pipe,
+}
+
+impl Connector {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Connector::Then => ";",
+            Connector::And => "&&",
+            Connector::Or => "||",
+            Connector::Pipe => "|",
+        }
+    }
+}
+
+/// Splits a console line into the commands chained with `;`, `&&`, `||` and `|`.
+///
+/// Operators inside quotes or escaped with a backslash are kept as part of the command,
+/// a `#` at the start of a word comments out the rest of the line.
+/// The commands are returned unparsed so they can be split into arguments later on.
+pub(crate) fn split_chain(line: &str) -> Result<Vec<(Connector, String)>, String> {
+    let mut chain = Vec::new();
+    let mut connector = Connector::Then;
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => {
+                quote = None;
+                current.push(c);
+            }
+            (Some('\''), _) => current.push(c),
+            (_, '\\') => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            (Some(_), _) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.push(c);
+            }
+            (None, '#') if current.is_empty() || current.ends_with(char::is_whitespace) => break,
+            (None, ';') => {
+                push_command(&mut chain, connector, &mut current, ";")?;
+                connector = Connector::Then;
+            }
+            (None, '&') if chars.peek() == Some(&'&') => {
+                chars.next();
+                push_command(&mut chain, connector, &mut current, "&&")?;
+                connector = Connector::And;
+            }
+            (None, '|') if chars.peek() == Some(&'|') => {
+                chars.next();
+                push_command(&mut chain, connector, &mut current, "||")?;
+                connector = Connector::Or;
+            }
+            (None, '|') => {
+                push_command(&mut chain, connector, &mut current, "|")?;
+                connector = Connector::Pipe;
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err("unterminated quote".into());
+    }
+
+    if current.trim().is_empty() {
+        if connector != Connector::Then {
+            return Err(format!(
+                "syntax error: expected a command after `{}`",
+                connector.as_str()
+            ));
+        }
+    } else {
+        chain.push((connector, current.trim().to_owned()));
+    }
+
+    Ok(chain)
+}
+
+fn push_command(
+    chain: &mut Vec<(Connector, String)>,
+    connector: Connector,
+    current: &mut String,
+    operator: &str,
+) -> Result<(), String> {
+    let command = current.trim();
+    if command.is_empty() {
+        // empty commands are only allowed between `;`, e.g. `foo;; bar`
+        if connector == Connector::Then && operator == ";" {
+            return Ok(());
+        }
+        return Err(format!("syntax error near `{operator}`"));
+    }
+    chain.push((connector, command.to_owned()));
+    current.clear();
+    Ok(())
+}
+
+/// Whether `name` can name a variable or an alias: letters, digits and `_`, so it is never split up or expanded
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_name_char)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Replaces `$name` and `${name}` in a command with the values returned by `lookup`.
+///
+/// Variables are not expanded inside single quotes or when the `$` is escaped with a backslash.
+/// Values are quoted so that they stay a single argument when the command is split into arguments.
+pub(crate) fn expand_vars(
+    command: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut expanded = String::with_capacity(command.len());
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                expanded.push(c);
+                if let Some(escaped) = chars.next() {
+                    expanded.push(escaped);
+                }
+                continue;
+            }
+            (None, '\'' | '"') => quote = Some(c),
+            (_, '$') => {
+                let name = if chars.next_if_eq(&'{').is_some() {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err("unterminated `${`".into()),
+                        }
+                    }
+                    name
+                } else {
+                    let mut name = String::new();
+                    while let Some(c) = chars.next_if(|&c| is_name_char(c)) {
+                        name.push(c);
+                    }
+                    name
+                };
+                if name.is_empty() {
+                    expanded.push('$');
+                    continue;
+                }
+
+                let value = lookup(&name).ok_or_else(|| format!("undefined variable '{name}'"))?;
+                if quote.is_some() {
+                    // inside double quotes
+                    for c in value.chars() {
+                        if matches!(c, '"' | '\\' | '$' | '`') {
+                            expanded.push('\\');
+                        }
+                        expanded.push(c);
+                    }
+                } else {
+                    let quoted = shlex::try_quote(&value)
+                        .map_err(|_| format!("invalid value of variable '{name}'"))?;
+                    expanded.push_str(&quoted);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        expanded.push(c);
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(items: &[(Connector, &str)]) -> Vec<(Connector, String)> {
+        items.iter().map(|(c, s)| (*c, s.to_string())).collect()
+    }
+
+    #[test]
+    fn test_single_command() {
+        assert_eq!(
+            split_chain("spawn enemy"),
+            Ok(chain(&[(Connector::Then, "spawn enemy")]))
+        );
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(
+            split_chain("spawn enemy; god && noclip || help"),
+            Ok(chain(&[
+                (Connector::Then, "spawn enemy"),
+                (Connector::Then, "god"),
+                (Connector::And, "noclip"),
+                (Connector::Or, "help"),
+            ]))
+        );
+        assert_eq!(
+            split_chain("entities | grep Enemy|count || help"),
+            Ok(chain(&[
+                (Connector::Then, "entities"),
+                (Connector::Pipe, "grep Enemy"),
+                (Connector::Pipe, "count"),
+                (Connector::Or, "help"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_quoted_operators_are_kept() {
+        assert_eq!(
+            split_chain(r#"log "a; b" 'c && d' e\;f"#),
+            Ok(chain(&[(Connector::Then, r#"log "a; b" 'c && d' e\;f"#)]))
+        );
+    }
+
+    #[test]
+    fn test_empty_commands_between_semicolons() {
+        assert_eq!(
+            split_chain("; foo;; bar;"),
+            Ok(chain(&[(Connector::Then, "foo"), (Connector::Then, "bar")]))
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(split_chain("# spawn enemy; god"), Ok(vec![]));
+        assert_eq!(
+            split_chain("spawn enemy#1 # ; god"),
+            Ok(chain(&[(Connector::Then, "spawn enemy#1")]))
+        );
+        assert_eq!(
+            split_chain("log '# not a comment'"),
+            Ok(chain(&[(Connector::Then, "log '# not a comment'")]))
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(split_chain("&& foo").is_err());
+        assert!(split_chain("foo &&").is_err());
+        assert!(split_chain("foo || ; bar").is_err());
+        assert!(split_chain("| foo").is_err());
+        assert!(split_chain("foo |").is_err());
+        assert!(split_chain("foo \"bar").is_err());
+    }
+
+    #[test]
+    fn test_expand_vars() {
+        let lookup = |name: &str| match name {
+            "fov" => Some("90".to_owned()),
+            "name" => Some("big boss".to_owned()),
+            "quote" => Some("say \"hi\"".to_owned()),
+            _ => None,
+        };
+        let expand = |command| expand_vars(command, lookup);
+
+        assert_eq!(expand("echo $fov ${fov}x"), Ok("echo 90 90x".into()));
+        assert_eq!(expand("spawn $name"), Ok("spawn 'big boss'".into()));
+        assert_eq!(expand("echo \"$name!\""), Ok("echo \"big boss!\"".into()));
+        assert_eq!(expand("echo \"$quote\""), Ok(r#"echo "say \"hi\"""#.into()));
+        assert_eq!(
+            expand("echo '$fov' \\$fov $ $1x"),
+            Err("undefined variable '1x'".into())
+        );
+        assert_eq!(
+            expand("echo '$fov' \\$fov $"),
+            Ok("echo '$fov' \\$fov $".into())
+        );
+        assert_eq!(
+            expand("echo $missing"),
+            Err("undefined variable 'missing'".into())
+        );
+        assert!(expand("echo ${fov").is_err());
+    }
+
+    #[test]
+    fn test_valid_names() {
+        assert!(is_valid_name("god_mode2"));
+        for name in ["", "a b", "a;b", "x|y", "$v", "#c", "a&&b"] {
+            assert!(!is_valid_name(name), "{name}");
+        }
+    }
+}
@@ -0,0 +1,603 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+use shlex::Shlex;
+
+use crate::commands::alias::ConsoleAliases;
+use crate::console::{
+    CommandSource, ConsoleCommandCompleted, ConsoleCommandEntered, ConsoleCommandSystems,
+    ConsoleConfiguration, Invocation, InvocationId, PrintConsoleLine,
+};
+use crate::parser::{expand_vars, split_chain, Connector};
+use crate::result::{CommandError, CommandResult, CommandValue};
+use crate::runner::CommandOutput;
+use crate::task::CommandTask;
+use crate::vars::ConsoleVars;
+
+/// Maximum number of nested alias expansions and scripts, protects against infinite recursion
+const MAX_EXPANSION_DEPTH: usize = 16;
+
+/// A command waiting to be executed
+struct QueuedCommand {
+    /// Console line the command was queued from
+    invocation: Invocation,
+    connector: Connector,
+    command: String,
+    /// Number of alias expansions and scripts which produced this command
+    depth: usize,
+    /// Script file and line the command was read from
+    location: Option<String>,
+}
+
+/// Console lines waiting to be executed, fed by the console, scripts, aliases and game code.
+///
+/// Commands are executed one at a time so that `&&` and `||` can depend on the result of the previous command,
+/// and `wait 10` or `wait 2s` suspends the remaining commands for a number of frames or seconds.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_console::{CommandQueue, CommandSource};
+/// fn boss_fight(mut queue: ResMut<CommandQueue>) {
+///     if let Err(err) = queue.push_line("spawn boss; wait 2s; screenshot", CommandSource::Code) {
+///         error!("{err}");
+///     }
+/// }
+/// ```
+#[derive(Resource)]
+pub struct CommandQueue {
+    commands: VecDeque<QueuedCommand>,
+    /// Command sent this frame, its result is collected next frame
+    running: Option<QueuedCommand>,
+    /// Result reported so far by the running command
+    running_result: CommandResult,
+    last_result: CommandResult,
+    /// Whether the last command was skipped, commands piped from it are skipped as well
+    last_skipped: bool,
+    /// Output of the running command, collected when it is piped into the next command
+    pipe: Option<Vec<String>>,
+    last_id: InvocationId,
+    /// Output of console lines being captured, see [`ConsoleRunner`](crate::ConsoleRunner)
+    pub(crate) captures: HashMap<InvocationId, CommandOutput>,
+    /// Output of captured console lines which finished executing, and the number of frames since
+    pub(crate) finished: HashMap<InvocationId, (CommandOutput, u32)>,
+    /// Tasks of commands running over several frames
+    pub(crate) tasks: Vec<CommandTask>,
+}
+
+impl Default for CommandQueue {
+    fn default() -> Self {
+        CommandQueue {
+            commands: VecDeque::new(),
+            running: None,
+            running_result: Ok(None),
+            last_result: Ok(None),
+            last_skipped: false,
+            pipe: None,
+            last_id: InvocationId(0),
+            captures: HashMap::new(),
+            finished: HashMap::new(),
+            tasks: Vec::new(),
+        }
+    }
+}
+
+impl CommandQueue {
+    /// Splits a console line into its chained commands and queues them for execution.
+    ///
+    /// Returns an error if the line can't be parsed.
+    pub fn push_line(&mut self, line: &str, source: CommandSource) -> Result<InvocationId, String> {
+        let invocation = Invocation {
+            id: InvocationId(self.last_id.0 + 1),
+            source,
+        };
+        let chain = parse_line(line, invocation, 0, None)?;
+        self.last_id = invocation.id;
+        self.commands.extend(chain);
+        Ok(invocation.id)
+    }
+
+    /// Queues a console line whose output is captured until taken with [`CommandQueue::take_output`].
+    pub(crate) fn push_captured_line(
+        &mut self,
+        line: &str,
+        source: CommandSource,
+    ) -> Result<InvocationId, String> {
+        let id = self.push_line(line, source)?;
+        self.captures.insert(id, CommandOutput::default());
+        Ok(id)
+    }
+
+    /// Takes the output of a captured console line once all of its commands finished.
+    pub(crate) fn take_output(&mut self, id: InvocationId) -> Option<CommandOutput> {
+        self.finished.remove(&id).map(|(output, _)| output)
+    }
+
+    /// Whether commands of the console line are still queued or running
+    pub fn is_pending(&self, id: InvocationId) -> bool {
+        self.running
+            .as_ref()
+            .is_some_and(|running| running.invocation.id == id)
+            || self
+                .commands
+                .iter()
+                .any(|command| command.invocation.id == id)
+    }
+
+    /// Cancels the running tasks and the remaining commands of the running console line.
+    ///
+    /// Returns `false` if there was nothing to cancel.
+    pub fn cancel(&mut self) -> bool {
+        for task in &self.tasks {
+            task.cancel();
+        }
+        let cancelled = self.commands.len();
+        if let Some(running) = &self.running {
+            let id = running.invocation.id;
+            self.commands.retain(|command| command.invocation.id != id);
+        }
+        !self.tasks.is_empty() || self.commands.len() != cancelled
+    }
+
+    /// Whether the line is printed by the running command and piped into the next command, errors are shown instead
+    pub(crate) fn is_piped(&self, line: &PrintConsoleLine) -> bool {
+        !line.error
+            && self.pipe.is_some()
+            && line.invocation.is_some_and(|invocation| {
+                self.running
+                    .as_ref()
+                    .is_some_and(|running| running.invocation.id == invocation.id)
+            })
+    }
+
+    /// Whether no command is queued or running
+    pub fn is_empty(&self) -> bool {
+        self.running.is_none() && self.commands.is_empty()
+    }
+
+    fn set_result(&mut self, id: InvocationId, result: CommandResult) {
+        if let Some(output) = self.captures.get_mut(&id) {
+            output.result = result.clone();
+        }
+        self.last_result = result;
+    }
+
+    /// Queues the lines of a script run by the running command, e.g. `exec`, so that they run right after it
+    /// as part of the same console line.
+    ///
+    /// Nothing is queued if any of the lines can't be parsed.
+    pub(crate) fn push_script(&mut self, path: &str, contents: &str) -> Result<(), String> {
+        let depth = self.running.as_ref().map_or(0, |running| running.depth + 1);
+        let invocation = self.running.as_ref().map_or(
+            Invocation {
+                id: self.last_id,
+                source: CommandSource::Script,
+            },
+            |running| running.invocation,
+        );
+        if depth > MAX_EXPANSION_DEPTH {
+            return Err(format!("{path}: scripts are nested too deeply"));
+        }
+
+        let mut commands = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let location = format!("{path}:{}", index + 1);
+            match parse_line(line, invocation, depth, Some(location.clone())) {
+                Ok(chain) => commands.extend(chain),
+                Err(err) => return Err(format!("{location}: {err}")),
+            }
+        }
+
+        for command in commands.into_iter().rev() {
+            self.commands.push_front(command);
+        }
+        Ok(())
+    }
+}
+
+fn parse_line(
+    line: &str,
+    invocation: Invocation,
+    depth: usize,
+    location: Option<String>,
+) -> Result<Vec<QueuedCommand>, String> {
+    Ok(split_chain(line)?
+        .into_iter()
+        .map(|(connector, command)| QueuedCommand {
+            invocation,
+            connector,
+            command,
+            depth,
+            location: location.clone(),
+        })
+        .collect())
+}
+
+/// Formats an error, prefixed with the script location of the command if there is one
+fn error_line(command: &QueuedCommand, msg: &str) -> PrintConsoleLine {
+    let line = match &command.location {
+        Some(location) => format!("error: {location}: {msg}"),
+        None => format!("error: {msg}"),
+    };
+    PrintConsoleLine::new(line)
+        .with_invocation(command.invocation)
+        .as_error()
+}
+
+/// Queues the startup scripts and commands from the [`ConsoleConfiguration`] and the command line
+pub(crate) fn queue_startup_commands(
+    config: Res<ConsoleConfiguration>,
+    mut queue: ResMut<CommandQueue>,
+    mut console_line: EventWriter<PrintConsoleLine>,
+) {
+    let mut lines = Vec::new();
+    for script in &config.startup_scripts {
+        let path = script.to_string_lossy();
+        match shlex::try_quote(&path) {
+            Ok(path) => lines.push(format!("exec {path}")),
+            Err(_) => warn!("Can't execute startup script '{path}'"),
+        }
+    }
+    lines.extend(config.startup_commands.iter().cloned());
+    if let Some(flag) = &config.command_line_flag {
+        lines.extend(command_line_commands(std::env::args().skip(1), flag));
+    }
+
+    for line in lines {
+        if let Err(err) = queue.push_line(&line, CommandSource::Script) {
+            console_line.write(PrintConsoleLine::new(format!("error: {err}")));
+        }
+    }
+}
+
+/// Collects the `+cmd` style commands passed to `flag`, either as `flag "+cmd"` or `flag="+cmd"`
+fn command_line_commands(mut args: impl Iterator<Item = String>, flag: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    while let Some(arg) = args.next() {
+        let value = if arg == flag {
+            args.next()
+        } else {
+            arg.strip_prefix(flag)
+                .and_then(|value| value.strip_prefix('='))
+                .map(str::to_owned)
+        };
+        if let Some(value) = value {
+            commands.extend(plus_commands(&value));
+        }
+    }
+    commands
+}
+
+/// Splits `+map arena +bots 4` into `map arena` and `bots 4`
+fn plus_commands(value: &str) -> Vec<String> {
+    let mut commands: Vec<Vec<String>> = Vec::new();
+    for word in Shlex::new(value) {
+        match (word.strip_prefix('+'), commands.last_mut()) {
+            (Some(name), _) if !name.is_empty() => commands.push(vec![name.to_owned()]),
+            (None, Some(command)) => command.push(word),
+            _ => warn!("Ignoring console argument `{word}`, commands must start with `+`"),
+        }
+    }
+    commands
+        .iter()
+        .filter_map(|command| shlex::try_join(command.iter().map(String::as_str)).ok())
+        .collect()
+}
+
+/// Sends queued commands as [`ConsoleCommandEntered`] events, waiting for each to complete before sending the next.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn execute_queue(
+    mut commands: Commands,
+    config: Res<ConsoleConfiguration>,
+    command_systems: Res<ConsoleCommandSystems>,
+    aliases: Res<ConsoleAliases>,
+    mut vars: ResMut<ConsoleVars>,
+    mut queue: ResMut<CommandQueue>,
+    mut completed: EventReader<ConsoleCommandCompleted>,
+    mut command_entered: EventWriter<ConsoleCommandEntered>,
+    mut console_line: EventWriter<PrintConsoleLine>,
+) {
+    // commands which don't report a result are assumed to have succeeded,
+    // the first error or else the last value is kept when several results are reported
+    let running_id = queue.running.as_ref().map(|running| running.invocation.id);
+    for completed in completed.read() {
+        // completions of other console lines don't belong to the running command
+        let other = completed
+            .invocation
+            .is_some_and(|invocation| Some(invocation.id) != running_id);
+        if !other && queue.running_result.is_ok() && completed.result != Ok(None) {
+            queue.running_result = completed.result.clone();
+        }
+    }
+    // commands running as tasks keep the queue waiting until they complete
+    let task_running = queue.tasks.iter().any(|task| {
+        task.invocation()
+            .is_some_and(|invocation| Some(invocation.id) == running_id)
+    });
+    if task_running {
+        return;
+    }
+    if let Some(running) = queue.running.take() {
+        let result = std::mem::replace(&mut queue.running_result, Ok(None));
+        if result.is_err() && running.location.is_some() {
+            console_line.write(error_line(
+                &running,
+                &format!("`{}` failed", running.command),
+            ));
+        }
+        queue.set_result(running.invocation.id, result);
+    }
+
+    while let Some(queued) = queue.commands.pop_front() {
+        let skip = match queued.connector {
+            Connector::Then => false,
+            Connector::And => queue.last_result.is_err(),
+            Connector::Or => queue.last_result.is_ok(),
+            // the output of a failed command isn't piped, the pipe fails with its error
+            Connector::Pipe => queue.last_skipped || queue.last_result.is_err(),
+        };
+        queue.last_skipped = skip;
+        let piped_output = queue.pipe.take();
+        if skip {
+            debug!("Skipping command `{}`", queued.command);
+            continue;
+        }
+
+        // variables are expanded when the command runs, so earlier commands can set them
+        let command = match expand_vars(&queued.command, |name| vars.expand(name)) {
+            Ok(command) => command,
+            Err(err) => {
+                console_line.write(error_line(&queued, &err));
+                queue.set_result(queued.invocation.id, Err(CommandError::new(err)));
+                continue;
+            }
+        };
+        let mut args = Shlex::new(&command).collect::<Vec<_>>();
+        if args.is_empty() {
+            continue;
+        }
+        let input = (queued.connector == Connector::Pipe).then(|| piped_output.unwrap_or_default());
+        let piped = queue
+            .commands
+            .front()
+            .is_some_and(|next| next.connector == Connector::Pipe);
+        let command_name = args.remove(0);
+        debug!("Command entered: `{command_name}`, with args: `{args:?}`");
+
+        if config.commands.contains_key(command_name.as_str()) {
+            if let Some(&id) = command_systems.0.get(command_name.as_str()) {
+                commands.run_system(id);
+            }
+            command_entered.write(ConsoleCommandEntered {
+                command_name,
+                args,
+                invocation: queued.invocation,
+                input,
+            });
+            if piped {
+                queue.pipe = Some(Vec::new());
+            }
+            queue.running = Some(queued);
+            return;
+        }
+
+        if vars.contains(&command_name) {
+            let result = match args.as_slice() {
+                [] => {
+                    if let Some(line) = vars.describe(&command_name) {
+                        if piped {
+                            queue.pipe = Some(vec![line]);
+                        } else {
+                            console_line.write(
+                                PrintConsoleLine::new(line).with_invocation(queued.invocation),
+                            );
+                        }
+                    }
+                    Ok(vars.get_string(&command_name).map(CommandValue::String))
+                }
+                [value] => vars.set_string(&command_name, value).map(|()| None),
+                _ => Err(format!("usage: {command_name} [value]")),
+            };
+            let result = result.map_err(|err| {
+                console_line.write(error_line(&queued, &err));
+                CommandError::new(err)
+            });
+            queue.set_result(queued.invocation.id, result);
+            continue;
+        }
+
+        if let Some(alias) = aliases.0.get(&command_name) {
+            let expanded = match shlex::try_join(args.iter().map(String::as_str)) {
+                Ok(args) if !args.is_empty() => format!("{alias} {args}"),
+                _ => alias.clone(),
+            };
+            debug!("Expanding alias `{command_name}` to `{expanded}`");
+
+            let result = if queued.depth >= MAX_EXPANSION_DEPTH {
+                Err(format!("alias `{command_name}` is nested too deeply"))
+            } else {
+                parse_line(
+                    &expanded,
+                    queued.invocation,
+                    queued.depth + 1,
+                    queued.location.clone(),
+                )
+            };
+            match result {
+                Ok(mut chain) => {
+                    // input piped into the alias goes to its first command
+                    if let (Some(input), Some(first)) = (input, chain.first_mut()) {
+                        first.connector = Connector::Pipe;
+                        queue.pipe = Some(input);
+                    }
+                    for command in chain.into_iter().rev() {
+                        queue.commands.push_front(command);
+                    }
+                }
+                Err(err) => {
+                    console_line.write(error_line(&queued, &err));
+                    queue.set_result(queued.invocation.id, Err(CommandError::new(err)));
+                }
+            }
+            continue;
+        }
+
+        debug!(
+            "Command not recognized, recognized commands: `{:?}`",
+            config.commands.keys().collect::<Vec<_>>()
+        );
+        console_line.write(error_line(&queued, "Invalid command"));
+        queue.set_result(
+            queued.invocation.id,
+            Err(CommandError::new("Invalid command").with_code(127)),
+        );
+    }
+}
+
+/// Collects the lines printed by a command whose output is piped into the next command
+pub(crate) fn collect_piped_output(
+    mut queue: ResMut<CommandQueue>,
+    mut console_line: EventReader<PrintConsoleLine>,
+) {
+    for line in console_line.read() {
+        if queue.is_piped(line) && !line.status {
+            if let Some(pipe) = queue.pipe.as_mut() {
+                pipe.push(line.line.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{run, test_app, NoteCommand};
+    use crate::{AddConsoleCommand, ConsoleCommand, ConsoleVar};
+
+    fn run_line(line: &str) -> Vec<String> {
+        run_line_with_aliases(line, &[])
+    }
+
+    fn run_line_with_aliases(line: &str, aliases: &[(&str, &str)]) -> Vec<String> {
+        let mut app = test_app();
+        app.world_mut().resource_mut::<ConsoleAliases>().0.extend(
+            aliases
+                .iter()
+                .map(|(name, command)| (name.to_string(), command.to_string())),
+        );
+        app.world_mut()
+            .resource_mut::<CommandQueue>()
+            .push_line(line, CommandSource::Test)
+            .unwrap();
+
+        run(&mut app)
+    }
+
+    #[test]
+    fn test_chained_commands() {
+        assert_eq!(run_line("mark a; mark b"), vec!["a", "b"]);
+        assert_eq!(run_line("mark a && mark b || mark c"), vec!["a", "b"]);
+        assert_eq!(run_line("fail && mark a || mark b; mark c"), vec!["b", "c"]);
+        assert_eq!(run_line("unknown || mark a"), vec!["a"]);
+    }
+
+    #[test]
+    fn test_only_entered_commands_run() {
+        #[derive(Resource, Default)]
+        struct Runs(usize);
+
+        let mut app = test_app();
+        app.init_resource::<Runs>()
+            .add_console_command::<NoteCommand, _>(
+                |_: ConsoleCommand<NoteCommand>, mut runs: ResMut<Runs>| runs.0 += 1,
+            );
+        app.world_mut()
+            .resource_mut::<CommandQueue>()
+            .push_line("mark a; fail; note b", CommandSource::Test)
+            .unwrap();
+        assert_eq!(run(&mut app), vec!["a"]);
+        assert_eq!(app.world().resource::<Runs>().0, 1);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let aliases = [("ab", "mark a; mark b"), ("loop", "loop"), ("f", "fail")];
+        assert_eq!(
+            run_line_with_aliases("ab; mark c", &aliases),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            run_line_with_aliases("ab && ab", &aliases),
+            vec!["a", "b", "a", "b"]
+        );
+        assert_eq!(
+            run_line_with_aliases("f && ab || mark c", &aliases),
+            vec!["c"]
+        );
+        assert_eq!(run_line_with_aliases("loop || mark a", &aliases), vec!["a"]);
+    }
+
+    #[test]
+    fn test_script() {
+        let mut app = test_app();
+        let mut queue = app.world_mut().resource_mut::<CommandQueue>();
+        queue.push_line("mark c", CommandSource::Test).unwrap();
+        queue
+            .push_script("test.cfg", "mark a\n# comment\n\nfail && mark x\nmark b")
+            .unwrap();
+        assert_eq!(run(&mut app), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_script_with_syntax_error() {
+        let mut queue = CommandQueue::default();
+        assert_eq!(
+            queue.push_script("test.cfg", "mark a\nmark \"b"),
+            Err("test.cfg:2: unterminated quote".into())
+        );
+        assert!(queue.commands.is_empty());
+    }
+
+    #[test]
+    fn test_command_line_commands() {
+        let args = [
+            "--fullscreen",
+            "--console",
+            "+map arena +bots 4",
+            "--console=+god",
+        ];
+        assert_eq!(
+            command_line_commands(args.into_iter().map(String::from), "--console"),
+            vec!["map arena", "bots 4", "god"]
+        );
+    }
+
+    #[test]
+    fn test_plus_commands() {
+        assert_eq!(
+            plus_commands(r#"ignored +log "hello world" +exec "my script.cfg" +"#),
+            vec!["log 'hello world'", "exec 'my script.cfg'"]
+        );
+    }
+
+    #[test]
+    fn test_vars() {
+        let mut app = test_app();
+        app.world_mut()
+            .resource_mut::<ConsoleVars>()
+            .register(ConsoleVar::new("bots", 0).range(0, 8));
+        app.world_mut()
+            .resource_mut::<CommandQueue>()
+            .push_line(
+                "bots 4 && mark a; bots 10 || mark b; bots",
+                CommandSource::Test,
+            )
+            .unwrap();
+        assert_eq!(run(&mut app), vec!["a", "b"]);
+        assert_eq!(
+            app.world().resource::<ConsoleVars>().get::<i32>("bots"),
+            Some(&4)
+        );
+    }
+}
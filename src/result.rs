@@ -0,0 +1,247 @@
+use std::fmt::{self, Display};
+
+use crate::PrintConsoleLine;
+
+/// Result of a console command, reported with [`ConsoleCommand::complete`](crate::ConsoleCommand::complete)
+/// and sent as a [`ConsoleCommandCompleted`](crate::ConsoleCommandCompleted) event.
+pub type CommandResult = Result<Option<CommandValue>, CommandError>;
+
+/// Value returned by a succeeding console command
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandValue {
+    /// A boolean
+    Bool(bool),
+    /// An integer
+    Int(i64),
+    /// An unsigned integer, e.g. a count
+    UInt(u64),
+    /// A floating point number
+    Float(f64),
+    /// A string
+    String(String),
+    /// A list of values
+    List(Vec<CommandValue>),
+}
+
+impl Display for CommandValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandValue::Bool(value) => value.fmt(f),
+            CommandValue::Int(value) => value.fmt(f),
+            CommandValue::UInt(value) => value.fmt(f),
+            CommandValue::Float(value) => value.fmt(f),
+            CommandValue::String(value) => value.fmt(f),
+            CommandValue::List(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    value.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident, $as:ty, $($ty:ty),*) => {
+        $(
+            impl From<$ty> for CommandValue {
+                fn from(value: $ty) -> Self {
+                    CommandValue::$variant(value as $as)
+                }
+            }
+        )*
+    };
+}
+
+impl_from!(Int, i64, i8, i16, i32, i64, isize, u8, u16, u32);
+impl_from!(UInt, u64, u64, usize);
+impl_from!(Float, f64, f32, f64);
+
+impl From<bool> for CommandValue {
+    fn from(value: bool) -> Self {
+        CommandValue::Bool(value)
+    }
+}
+
+impl From<String> for CommandValue {
+    fn from(value: String) -> Self {
+        CommandValue::String(value)
+    }
+}
+
+impl From<&str> for CommandValue {
+    fn from(value: &str) -> Self {
+        CommandValue::String(value.to_owned())
+    }
+}
+
+impl<T: Into<CommandValue>> From<Vec<T>> for CommandValue {
+    fn from(values: Vec<T>) -> Self {
+        CommandValue::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Error of a failing console command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandError {
+    /// Message printed to the console, may be empty
+    pub message: String,
+    /// Error code, `1` unless specified otherwise
+    pub code: i32,
+}
+
+impl CommandError {
+    /// Creates an error with code `1`.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            code: 1,
+        }
+    }
+
+    /// Sets the error code.
+    pub fn with_code(mut self, code: i32) -> Self {
+        self.code = code;
+        self
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::new(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::new(message)
+    }
+}
+
+/// Values which can be returned by a succeeding command, `()` for commands without a value.
+pub trait IntoCommandValue {
+    /// Converts into the value of the command
+    fn into_command_value(self) -> Option<CommandValue>;
+}
+
+impl IntoCommandValue for () {
+    fn into_command_value(self) -> Option<CommandValue> {
+        None
+    }
+}
+
+impl<T: Into<CommandValue>> IntoCommandValue for Option<T> {
+    fn into_command_value(self) -> Option<CommandValue> {
+        self.map(Into::into)
+    }
+}
+
+macro_rules! impl_into_command_value {
+    ($($ty:ty),*) => {
+        $(
+            impl IntoCommandValue for $ty {
+                fn into_command_value(self) -> Option<CommandValue> {
+                    Some(self.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_into_command_value!(
+    CommandValue,
+    bool,
+    i8,
+    i16,
+    i32,
+    i64,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    f32,
+    f64,
+    String,
+    &str
+);
+
+impl<T: Into<CommandValue>> IntoCommandValue for Vec<T> {
+    fn into_command_value(self) -> Option<CommandValue> {
+        Some(self.into())
+    }
+}
+
+/// Lines printed to the console for a command result, errors and the status are marked so they aren't piped
+pub(crate) fn result_lines(result: &CommandResult) -> Vec<PrintConsoleLine> {
+    let ok = PrintConsoleLine::new("[ok]".into()).as_status();
+    match result {
+        Ok(None) => vec![ok],
+        Ok(Some(value)) => vec![PrintConsoleLine::new(value.to_string()), ok],
+        Err(err) => {
+            let mut lines = Vec::new();
+            if !err.message.is_empty() {
+                lines.push(PrintConsoleLine::new(err.message.clone()).as_error());
+            }
+            let failed = if err.code == 1 {
+                "[failed]".into()
+            } else {
+                format!("[failed] (code {})", err.code)
+            };
+            lines.push(PrintConsoleLine::new(failed).as_error().as_status());
+            lines
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_lines() {
+        let lines = |result| {
+            result_lines(&result)
+                .into_iter()
+                .map(|line| (line.line, line.error, line.status))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(Ok(None)), vec![("[ok]".into(), false, true)]);
+        assert_eq!(
+            lines(Ok(vec![1, 2, 3].into_command_value())),
+            vec![("1 2 3".into(), false, false), ("[ok]".into(), false, true)]
+        );
+        assert_eq!(
+            lines(Err(CommandError::new("no such entity"))),
+            vec![
+                ("no such entity".into(), true, false),
+                ("[failed]".into(), true, true)
+            ]
+        );
+        assert_eq!(
+            lines(Err(CommandError::new("").with_code(127))),
+            vec![("[failed] (code 127)".into(), true, true)]
+        );
+    }
+
+    #[test]
+    fn test_unsigned_values() {
+        assert_eq!(CommandValue::from(42u32), CommandValue::Int(42));
+        assert_eq!(CommandValue::from(42usize), CommandValue::UInt(42));
+        assert_eq!(CommandValue::from(u64::MAX), CommandValue::UInt(u64::MAX));
+        assert_eq!(
+            CommandValue::from(u64::MAX).to_string(),
+            "18446744073709551615"
+        );
+    }
+}
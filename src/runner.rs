@@ -0,0 +1,231 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::queue::CommandQueue;
+use crate::result::CommandResult;
+use crate::{CommandSource, InvocationId, PrintConsoleLine};
+
+/// Number of frames the output of a console line is kept once it finished, unless taken before
+const OUTPUT_KEPT_FRAMES: u32 = 600;
+
+/// Number of frames [`RunConsoleLine::run_console_line`] waits for a console line to finish
+const RUN_FRAME_LIMIT: u32 = 10_000;
+
+/// Everything printed by the commands of a console line, and the result of the last command which ran
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandOutput {
+    /// Lines printed by the commands
+    pub lines: Vec<String>,
+    /// Result of the last command which ran
+    pub result: CommandResult,
+}
+
+impl Default for CommandOutput {
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            result: Ok(None),
+        }
+    }
+}
+
+/// Executes console lines and captures their output.
+///
+/// Commands run one per frame, so the output is available once all commands of the line finished.
+/// It is kept for 600 frames, lines only run for their effects don't need their output taken.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_console::{ConsoleRunner, InvocationId};
+/// fn count_enemies(mut runner: ConsoleRunner, mut pending: Local<Option<InvocationId>>) {
+///     match *pending {
+///         None => *pending = runner.run("entities enemy").ok(),
+///         Some(id) => {
+///             if let Some(output) = runner.take_output(id) {
+///                 info!("{} enemies", output.lines.len());
+///                 *pending = None;
+///             }
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct ConsoleRunner<'w> {
+    queue: ResMut<'w, CommandQueue>,
+}
+
+impl ConsoleRunner<'_> {
+    /// Queues a console line for execution, capturing its output.
+    ///
+    /// Returns an error if the line can't be parsed.
+    pub fn run(&mut self, line: &str) -> Result<InvocationId, String> {
+        self.run_with_source(line, CommandSource::Code)
+    }
+
+    /// Queues a console line entered from the given source, e.g. a remote client, capturing its output.
+    pub fn run_with_source(
+        &mut self,
+        line: &str,
+        source: CommandSource,
+    ) -> Result<InvocationId, String> {
+        self.queue.push_captured_line(line, source)
+    }
+
+    /// Takes the output of a console line queued with [`ConsoleRunner::run`], once all of its commands finished.
+    ///
+    /// Returns `None` until then, and once the output was taken or dropped 600 frames after the line finished.
+    pub fn take_output(&mut self, id: InvocationId) -> Option<CommandOutput> {
+        self.queue.take_output(id)
+    }
+}
+
+/// Executes console lines from an [`App`], e.g. in tests.
+pub trait RunConsoleLine {
+    /// Executes a console line, updating the app until all of its commands finished.
+    ///
+    /// Returns an error if the line can't be parsed,
+    /// or didn't finish within 10 000 frames, e.g. because a task never completes.
+    fn run_console_line(&mut self, line: &str) -> Result<CommandOutput, String>;
+}
+
+impl RunConsoleLine for App {
+    fn run_console_line(&mut self, line: &str) -> Result<CommandOutput, String> {
+        let id = self
+            .world_mut()
+            .resource_mut::<CommandQueue>()
+            .push_captured_line(line, CommandSource::Test)?;
+        for _ in 0..RUN_FRAME_LIMIT {
+            self.update();
+            if let Some(output) = self
+                .world_mut()
+                .resource_mut::<CommandQueue>()
+                .take_output(id)
+            {
+                return Ok(output);
+            }
+        }
+        // the output of the line is no longer needed
+        self.world_mut()
+            .resource_mut::<CommandQueue>()
+            .captures
+            .remove(&id);
+        Err(format!(
+            "`{line}` didn't finish within {RUN_FRAME_LIMIT} frames"
+        ))
+    }
+}
+
+/// Collects the lines printed by captured console lines, and their output once all of their commands finished
+pub(crate) fn capture_output(
+    mut queue: ResMut<CommandQueue>,
+    mut console_line: EventReader<PrintConsoleLine>,
+) {
+    for line in console_line.read() {
+        let Some(invocation) = line.invocation else {
+            continue;
+        };
+        if queue.is_piped(line) {
+            continue;
+        }
+        if let Some(output) = queue.captures.get_mut(&invocation.id) {
+            output.lines.push(line.line.clone());
+        }
+    }
+
+    let finished = queue
+        .captures
+        .keys()
+        .copied()
+        .filter(|&id| !queue.is_pending(id))
+        .collect::<Vec<_>>();
+    // outputs which aren't taken are dropped after a while
+    queue.finished.retain(|_, (_, frames)| {
+        *frames += 1;
+        *frames <= OUTPUT_KEPT_FRAMES
+    });
+    for id in finished {
+        if let Some(output) = queue.captures.remove(&id) {
+            queue.finished.insert(id, (output, 0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::{CommandError, CommandValue};
+    use crate::testing::test_app;
+    use crate::{AddConsoleCommand, ConsoleVar, ConsoleVars};
+
+    #[test]
+    fn test_frame_limit() {
+        let mut app = test_app();
+        // `Time` doesn't advance in the test app
+        assert_eq!(
+            app.run_console_line("wait 1s"),
+            Err(format!(
+                "`wait 1s` didn't finish within {RUN_FRAME_LIMIT} frames"
+            ))
+        );
+        assert!(app.world().resource::<CommandQueue>().captures.is_empty());
+    }
+
+    #[test]
+    fn test_uncollected_output() {
+        let mut app = test_app();
+        let mut queue = app.world_mut().resource_mut::<CommandQueue>();
+        let taken = queue
+            .push_captured_line("mark a", CommandSource::Code)
+            .unwrap();
+        let dropped = queue
+            .push_captured_line("mark b", CommandSource::Code)
+            .unwrap();
+        for _ in 0..10 {
+            app.update();
+        }
+
+        let mut queue = app.world_mut().resource_mut::<CommandQueue>();
+        assert!(queue.take_output(taken).is_some());
+        assert!(queue.finished.contains_key(&dropped));
+        for _ in 0..OUTPUT_KEPT_FRAMES {
+            app.update();
+        }
+        let mut queue = app.world_mut().resource_mut::<CommandQueue>();
+        assert!(queue.take_output(dropped).is_none());
+        assert!(queue.finished.is_empty());
+    }
+
+    #[test]
+    fn test_captured_output() {
+        let mut app = test_app();
+        app.world_mut()
+            .resource_mut::<ConsoleVars>()
+            .register(ConsoleVar::new("bots", 0).range(0, 8));
+        app.add_console_fn(
+            "sum",
+            "Adds two numbers",
+            |a: i64, b: i64, _: &mut World| a + b,
+        );
+
+        let output = app.run_console_line("bots 4; bots; sum 1 2").unwrap();
+        assert_eq!(output.lines, vec!["bots = 4 (default 0)", "3", "[ok]"]);
+        assert_eq!(output.result, Ok(Some(CommandValue::Int(3))));
+
+        // output of other lines is not captured
+        app.world_mut()
+            .resource_mut::<CommandQueue>()
+            .push_line("bots", CommandSource::Test)
+            .unwrap();
+        let output = app.run_console_line("fail || bots 10").unwrap();
+        assert_eq!(
+            output.lines,
+            vec!["[failed]", "error: 'bots' must be between 0 and 8"]
+        );
+        assert_eq!(
+            output.result,
+            Err(CommandError::new("'bots' must be between 0 and 8"))
+        );
+    }
+}
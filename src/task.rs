@@ -0,0 +1,222 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bevy::prelude::*;
+
+use crate::queue::CommandQueue;
+use crate::result::{result_lines, CommandError, CommandResult, CommandValue};
+use crate::{ConsoleCommandCompleted, Invocation, PrintConsoleLine};
+
+/// Handle of a console command running over several frames, returned by
+/// [`ConsoleCommand::start_task`](crate::ConsoleCommand::start_task).
+///
+/// The console waits for the task before executing the next command,
+/// and the system of a command added with [`add_console_command`](crate::AddConsoleCommand::add_console_command)
+/// keeps running every frame until the task completes.
+/// The handle can be cloned and sent to other threads, the task completes successfully
+/// once all handles are dropped if it wasn't completed before.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_console::{CommandTask, ConsoleCommand};
+/// # use clap::Parser;
+/// /// Runs the game at full speed for a number of frames
+/// #[derive(Parser, ConsoleCommand)]
+/// #[command(name = "soak_test")]
+/// struct SoakTestCommand {
+///     frames: u32,
+/// }
+///
+/// fn soak_test_command(
+///     mut soak_test: ConsoleCommand<SoakTestCommand>,
+///     mut running: Local<Option<(CommandTask, u32, u32)>>,
+/// ) {
+///     if let Some(Ok(SoakTestCommand { frames })) = soak_test.take() {
+///         *running = Some((soak_test.start_task(), 0, frames));
+///     }
+///
+///     if let Some((task, frame, frames)) = running.as_mut() {
+///         *frame += 1;
+///         task.set_progress(*frame as f32 / *frames as f32);
+///         if task.is_cancelled() || *frame >= *frames {
+///             task.ok();
+///             *running = None;
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CommandTask {
+    state: Arc<Mutex<TaskState>>,
+}
+
+#[derive(Default)]
+struct TaskState {
+    command_name: String,
+    invocation: Option<Invocation>,
+    /// Lines printed since the last frame
+    lines: Vec<PrintConsoleLine>,
+    progress: Option<f32>,
+    cancelled: bool,
+    result: Option<CommandResult>,
+}
+
+impl CommandTask {
+    pub(crate) fn new(command_name: &str, invocation: Option<Invocation>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TaskState {
+                command_name: command_name.to_owned(),
+                invocation,
+                ..default()
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, TaskState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Print a reply in the console.
+    pub fn reply(&self, msg: impl Into<String>) {
+        self.state().lines.push(PrintConsoleLine::new(msg.into()));
+    }
+
+    /// Set the progress shown in the console, from `0.0` to `1.0`.
+    pub fn set_progress(&self, progress: f32) {
+        self.state().progress = Some(progress.clamp(0.0, 1.0));
+    }
+
+    /// Whether the task was cancelled with Ctrl+C, the task should stop as soon as possible.
+    pub fn is_cancelled(&self) -> bool {
+        self.state().cancelled
+    }
+
+    /// Complete the task with a result, printing the value or error in the console.
+    ///
+    /// Only the first result is used, cancelled tasks always fail.
+    pub fn complete(&self, result: CommandResult) {
+        let mut state = self.state();
+        if state.result.is_none() && !state.cancelled {
+            state.lines.extend(result_lines(&result));
+            state.result = Some(result);
+        }
+    }
+
+    /// Complete the task successfully, printing `[ok]` in the console.
+    pub fn ok(&self) {
+        self.complete(Ok(None));
+    }
+
+    /// Complete the task with a value, printing it followed by `[ok]` in the console.
+    pub fn ok_with(&self, value: impl Into<CommandValue>) {
+        self.complete(Ok(Some(value.into())));
+    }
+
+    /// Complete the task with an error, printing it followed by `[failed]` in the console.
+    pub fn failed_with(&self, error: impl Into<CommandError>) {
+        self.complete(Err(error.into()));
+    }
+
+    pub(crate) fn invocation(&self) -> Option<Invocation> {
+        self.state().invocation
+    }
+
+    /// Name of the command and progress of the task, if reported
+    pub(crate) fn progress(&self) -> (String, Option<f32>) {
+        let state = self.state();
+        (state.command_name.clone(), state.progress)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.state().cancelled = true;
+    }
+}
+
+/// Sent when a command starts a [`CommandTask`]
+#[derive(Event)]
+pub(crate) struct TaskStarted(pub(crate) CommandTask);
+
+/// Prints the replies of running tasks, and completes tasks which finished or were cancelled
+pub(crate) fn update_tasks(
+    mut queue: ResMut<CommandQueue>,
+    mut started: EventReader<TaskStarted>,
+    mut console_line: EventWriter<PrintConsoleLine>,
+    mut completed: EventWriter<ConsoleCommandCompleted>,
+) {
+    queue
+        .tasks
+        .extend(started.read().map(|started| started.0.clone()));
+
+    queue.tasks.retain(|task| {
+        // the queue holds the only handle once the command dropped its handles
+        let dropped = Arc::strong_count(&task.state) == 1;
+        let mut guard = task.state();
+        let state = &mut *guard;
+        if state.cancelled && state.result.is_none() {
+            let result = Err(CommandError::new("cancelled").with_code(130));
+            state.lines.extend(result_lines(&result));
+            state.result = Some(result);
+        }
+
+        for mut line in state.lines.drain(..) {
+            line.invocation = state.invocation;
+            console_line.write(line);
+        }
+
+        if state.result.is_none() && !dropped {
+            return true;
+        }
+        let mut event = ConsoleCommandCompleted::new(
+            state.command_name.clone(),
+            state.result.take().unwrap_or(Ok(None)),
+        );
+        event.invocation = state.invocation;
+        completed.write(event);
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{test_app, Marks};
+    use crate::{CommandSource, RunConsoleLine};
+
+    #[test]
+    fn test_tasks() {
+        let mut app = test_app();
+        let output = app.run_console_line("countdown 3 && mark a").unwrap();
+        assert_eq!(output.lines, vec!["3", "2", "1", "[ok]"]);
+        assert_eq!(output.result, Ok(None));
+        assert_eq!(app.world().resource::<Marks>().0, vec!["a"]);
+
+        // cancelling skips the remaining commands of the line
+        let id = app
+            .world_mut()
+            .resource_mut::<CommandQueue>()
+            .push_captured_line("countdown 100; mark b", CommandSource::Test)
+            .unwrap();
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(app.world_mut().resource_mut::<CommandQueue>().cancel());
+        let output = loop {
+            app.update();
+            if let Some(output) = app
+                .world_mut()
+                .resource_mut::<CommandQueue>()
+                .take_output(id)
+            {
+                break output;
+            }
+        };
+        assert_eq!(
+            output.result,
+            Err(CommandError::new("cancelled").with_code(130))
+        );
+        assert_eq!(output.lines.last().unwrap(), "[failed] (code 130)");
+        assert_eq!(app.world().resource::<Marks>().0, vec!["a"]);
+        assert!(!app.world_mut().resource_mut::<CommandQueue>().cancel());
+    }
+}
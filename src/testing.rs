@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContextPass;
+use clap::Parser;
+
+use crate as bevy_console;
+use crate::{add_command_systems, AddConsoleCommand, CommandTask, ConsoleCommand};
+
+/// Fails, with an error message if given
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "fail")]
+pub(crate) struct FailCommand {
+    message: Option<String>,
+}
+
+fn fail_command(mut fail: ConsoleCommand<FailCommand>) {
+    if let Some(Ok(FailCommand { message })) = fail.take() {
+        fail.failed_with(message.unwrap_or_default());
+    }
+}
+
+/// Records its argument
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "mark")]
+pub(crate) struct MarkCommand {
+    pub(crate) name: String,
+}
+
+/// Arguments of the `mark` commands run so far
+#[derive(Resource, Default)]
+pub(crate) struct Marks(pub(crate) Vec<String>);
+
+fn mark_command(mut mark: ConsoleCommand<MarkCommand>, mut marks: ResMut<Marks>) {
+    if let Some(Ok(MarkCommand { name })) = mark.take() {
+        marks.0.push(name);
+    }
+}
+
+/// Records its argument prefixed with `note`, not added to the test app
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "note")]
+pub(crate) struct NoteCommand {
+    pub(crate) name: String,
+}
+
+pub(crate) fn note_command(mut note: ConsoleCommand<NoteCommand>, mut marks: ResMut<Marks>) {
+    if let Some(Ok(NoteCommand { name })) = note.take() {
+        marks.0.push(format!("note {name}"));
+    }
+}
+
+/// Counts down over several frames
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "countdown")]
+pub(crate) struct CountdownCommand {
+    frames: u32,
+}
+
+fn countdown_command(
+    mut countdown: ConsoleCommand<CountdownCommand>,
+    mut running: Local<Option<(CommandTask, u32)>>,
+) {
+    if let Some(Ok(CountdownCommand { frames })) = countdown.take() {
+        *running = Some((countdown.start_task(), frames));
+    }
+
+    if let Some((task, frames)) = running.as_mut() {
+        if task.is_cancelled() || *frames == 0 {
+            task.ok();
+            *running = None;
+        } else {
+            task.reply(frames.to_string());
+            *frames -= 1;
+        }
+    }
+}
+
+/// App running the console commands as the plugin does, without the console window.
+///
+/// Adds the `fail`, `mark` and `countdown` commands for testing.
+pub(crate) fn test_app() -> App {
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .init_resource::<ButtonInput<KeyCode>>()
+        .init_resource::<Marks>()
+        .add_event::<AppExit>();
+    add_command_systems(&mut app);
+    app.add_console_command::<FailCommand, _>(fail_command)
+        .add_console_command::<MarkCommand, _>(mark_command)
+        .add_console_command::<CountdownCommand, _>(countdown_command)
+        // run by egui every frame, which isn't added in tests
+        .add_systems(Last, |world: &mut World| {
+            world.run_schedule(EguiContextPass);
+        });
+    app
+}
+
+/// Updates the app for a few frames and takes the names marked so far
+pub(crate) fn run(app: &mut App) -> Vec<String> {
+    for _ in 0..10 {
+        app.update();
+    }
+
+    app.world_mut().remove_resource::<Marks>().unwrap().0
+}
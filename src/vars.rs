@@ -0,0 +1,518 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+
+use crate::ConsoleConfiguration;
+
+/// Types which can be stored in a [`ConsoleVar`].
+pub trait ConsoleVarValue: Clone + PartialOrd + Display + FromStr + Send + Sync + 'static {}
+impl<T: Clone + PartialOrd + Display + FromStr + Send + Sync + 'static> ConsoleVarValue for T {}
+
+/// A typed console variable, readable and writable from the console with `name [value]`.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_console::{AddConsoleVar, ConsoleVar};
+/// App::new().add_console_var(
+///     ConsoleVar::new("fov", 90.0)
+///         .description("Field of view in degrees")
+///         .range(60.0, 120.0),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConsoleVar<T> {
+    name: String,
+    value: T,
+    default: T,
+    description: String,
+    min: Option<T>,
+    max: Option<T>,
+    archive: bool,
+}
+
+impl<T: ConsoleVarValue> ConsoleVar<T> {
+    /// Creates a new console variable with a default value.
+    pub fn new(name: impl Into<String>, default: T) -> Self {
+        Self {
+            name: name.into(),
+            value: default.clone(),
+            default,
+            description: String::new(),
+            min: None,
+            max: None,
+            archive: false,
+        }
+    }
+
+    /// Sets the description shown in the console.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Sets the minimum and maximum allowed values.
+    pub fn range(mut self, min: T, max: T) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    /// Sets the minimum allowed value.
+    pub fn min(mut self, min: T) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// Sets the maximum allowed value.
+    pub fn max(mut self, max: T) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Saves the variable to the config file with `writeconfig`,
+    /// see [`ConsoleConfiguration::config_file`](crate::ConsoleConfiguration::config_file).
+    pub fn archive(mut self) -> Self {
+        self.archive = true;
+        self
+    }
+
+    /// Name of the variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Current value.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Default value.
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    fn validate(&self, value: &T) -> Result<(), String> {
+        // written so that values which can't be compared, such as NaN, are out of range
+        let in_range = self.min.as_ref().is_none_or(|min| min <= value)
+            && self.max.as_ref().is_none_or(|max| value <= max);
+        match (&self.min, &self.max) {
+            _ if in_range => Ok(()),
+            (Some(min), Some(max)) => {
+                Err(format!("'{}' must be between {min} and {max}", self.name))
+            }
+            (Some(min), None) => Err(format!("'{}' must be at least {min}", self.name)),
+            (None, Some(max)) => Err(format!("'{}' must be at most {max}", self.name)),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Sets the value, returns `true` if it changed.
+    fn set(&mut self, value: T) -> Result<bool, String> {
+        self.validate(&value)?;
+        let changed = self.value != value;
+        self.value = value;
+        Ok(changed)
+    }
+}
+
+/// Type erased [`ConsoleVar`]
+trait AnyConsoleVar: Send + Sync {
+    fn description(&self) -> &str;
+    fn is_archived(&self) -> bool;
+    fn value_string(&self) -> String;
+    fn default_string(&self) -> String;
+    fn set_str(&mut self, value: &str) -> Result<bool, String>;
+    fn reset(&mut self) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: ConsoleVarValue> AnyConsoleVar for ConsoleVar<T> {
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn is_archived(&self) -> bool {
+        self.archive
+    }
+
+    fn value_string(&self) -> String {
+        self.value.to_string()
+    }
+
+    fn default_string(&self) -> String {
+        self.default.to_string()
+    }
+
+    fn set_str(&mut self, value: &str) -> Result<bool, String> {
+        let parsed = value
+            .parse::<T>()
+            .map_err(|_| format!("Invalid value '{value}' for '{}'", self.name))?;
+        self.set(parsed)
+    }
+
+    fn reset(&mut self) -> bool {
+        let changed = self.value != self.default;
+        self.value = self.default.clone();
+        changed
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Emitted when the value of a console variable changes.
+#[derive(Clone, Debug, Eq, Event, PartialEq)]
+pub struct ConsoleVarChanged {
+    /// Name of the variable
+    pub name: String,
+}
+
+/// Registered console variables.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_console::ConsoleVars;
+/// fn apply_fov(vars: Res<ConsoleVars>) {
+///     let fov = vars.get::<f32>("fov").copied().unwrap_or(90.0);
+/// }
+/// ```
+#[derive(Resource, Default)]
+pub struct ConsoleVars {
+    vars: BTreeMap<String, Box<dyn AnyConsoleVar>>,
+    changed: Vec<String>,
+    /// Variables defined with `set` in the console
+    user: BTreeMap<String, String>,
+}
+
+impl ConsoleVars {
+    /// Registers a variable, replacing any variable with the same name.
+    ///
+    /// Panics in debug builds if the default value is out of the range of the variable.
+    /// A variable named like a command can't be used from the console, which is logged as a warning.
+    pub fn register<T: ConsoleVarValue>(&mut self, var: ConsoleVar<T>) {
+        // also catches a minimum greater than the maximum, as no default fits in that range
+        debug_assert!(
+            var.validate(&var.default).is_ok(),
+            "the default of console variable '{}' is out of its range",
+            var.name
+        );
+        if self.vars.contains_key(&var.name) {
+            warn!(
+                "console variable '{}' already registered and was overwritten",
+                var.name
+            );
+        }
+        self.vars.insert(var.name.clone(), Box::new(var));
+    }
+
+    /// Returns `true` if a variable with the given name is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.vars.contains_key(name)
+    }
+
+    /// Returns the variable, if it is registered with type `T`.
+    pub fn get_var<T: ConsoleVarValue>(&self, name: &str) -> Option<&ConsoleVar<T>> {
+        self.vars.get(name)?.as_any().downcast_ref()
+    }
+
+    /// Returns the value of the variable, if it is registered with type `T`.
+    pub fn get<T: ConsoleVarValue>(&self, name: &str) -> Option<&T> {
+        self.get_var(name).map(ConsoleVar::value)
+    }
+
+    /// Returns the value of the variable formatted as a string.
+    pub fn get_string(&self, name: &str) -> Option<String> {
+        self.vars.get(name).map(|var| var.value_string())
+    }
+
+    /// Sets the value of a variable registered with type `T`.
+    pub fn set<T: ConsoleVarValue>(&mut self, name: &str, value: T) -> Result<(), String> {
+        let var = self
+            .vars
+            .get_mut(name)
+            .ok_or_else(|| format!("Variable '{name}' does not exist"))?
+            .as_any_mut()
+            .downcast_mut::<ConsoleVar<T>>()
+            .ok_or_else(|| format!("Variable '{name}' has a different type"))?;
+        if var.set(value)? {
+            self.changed.push(name.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Parses and sets the value of a variable.
+    pub fn set_string(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let var = self
+            .vars
+            .get_mut(name)
+            .ok_or_else(|| format!("Variable '{name}' does not exist"))?;
+        if var.set_str(value)? {
+            self.changed.push(name.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Resets a variable to its default value.
+    pub fn reset(&mut self, name: &str) -> Result<(), String> {
+        let var = self
+            .vars
+            .get_mut(name)
+            .ok_or_else(|| format!("Variable '{name}' does not exist"))?;
+        if var.reset() {
+            self.changed.push(name.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Names of all registered variables.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.vars.keys().map(String::as_str)
+    }
+
+    /// Sets a user defined variable, as done with `set name value` in the console.
+    pub fn set_user(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.user.insert(name.into(), value.into());
+    }
+
+    /// Removes a user defined variable, returns `false` if it wasn't defined.
+    pub fn unset_user(&mut self, name: &str) -> bool {
+        self.user.remove(name).is_some()
+    }
+
+    /// User defined variables and their values.
+    pub fn user_vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.user
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Value substituted for `$name` in console lines, of a registered or user defined variable.
+    pub fn expand(&self, name: &str) -> Option<String> {
+        self.get_string(name)
+            .or_else(|| self.user.get(name).cloned())
+    }
+
+    /// Formats all archived variables as a TOML table, numbers and booleans as typed values.
+    pub fn archived_to_toml(&self) -> String {
+        let table = self
+            .vars
+            .iter()
+            .filter(|(_, var)| var.is_archived())
+            .map(|(name, var)| (name.clone(), toml_value(var.value_string())))
+            .collect::<toml::Table>();
+        table.to_string()
+    }
+
+    /// Sets variables from a TOML table, as written by [`ConsoleVars::archived_to_toml`].
+    ///
+    /// Values which can't be set are skipped and returned as errors.
+    pub fn load_toml(&mut self, toml: &str) -> Result<(), Vec<String>> {
+        let table = toml
+            .parse::<toml::Table>()
+            .map_err(|err| vec![err.to_string()])?;
+        let errors = table
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let value = match value {
+                    toml::Value::String(value) => value,
+                    value => value.to_string(),
+                };
+                self.set_string(&name, &value).err()
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Describes a variable as shown in the console, e.g. `fov = 90 (default 90) - Field of view`.
+    pub(crate) fn describe(&self, name: &str) -> Option<String> {
+        let var = self.vars.get(name)?;
+        let mut line = format!(
+            "{name} = {} (default {})",
+            var.value_string(),
+            var.default_string()
+        );
+        if !var.description().is_empty() {
+            line.push_str(&format!(" - {}", var.description()));
+        }
+        Some(line)
+    }
+}
+
+/// Add console variables to a Bevy app.
+pub trait AddConsoleVar {
+    /// Registers a console variable.
+    ///
+    /// The variable can be read with `name` and written with `name value` in the console.
+    fn add_console_var<T: ConsoleVarValue>(&mut self, var: ConsoleVar<T>) -> &mut Self;
+}
+
+impl AddConsoleVar for App {
+    fn add_console_var<T: ConsoleVarValue>(&mut self, var: ConsoleVar<T>) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<ConsoleVars>()
+            .register(var);
+        self
+    }
+}
+
+/// Loads the variables saved in [`ConsoleConfiguration::config_file`]
+pub(crate) fn load_config_file(config: Res<ConsoleConfiguration>, mut vars: ResMut<ConsoleVars>) {
+    let Some(path) = &config.config_file else {
+        return;
+    };
+    match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            if let Err(errors) = vars.load_toml(&contents) {
+                for err in errors {
+                    warn!("{}: {err}", path.display());
+                }
+            }
+        }
+        // the config file is only written by `writeconfig`
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => warn!("Failed to read '{}': {err}", path.display()),
+    }
+}
+
+/// Sends [`ConsoleVarChanged`] events for variables changed since the last run
+pub(crate) fn send_console_var_changed(
+    mut vars: ResMut<ConsoleVars>,
+    mut changed: EventWriter<ConsoleVarChanged>,
+) {
+    if vars.changed.is_empty() {
+        return;
+    }
+    for name in vars.changed.drain(..) {
+        changed.write(ConsoleVarChanged { name });
+    }
+}
+
+/// Typed TOML value of a variable, e.g. a number or a boolean, or a string if it doesn't format back the same
+fn toml_value(value: String) -> toml::Value {
+    format!("value = {value}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .filter(|parsed| !parsed.is_str() && parsed.to_string() == value)
+        .unwrap_or(toml::Value::String(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> ConsoleVars {
+        let mut vars = ConsoleVars::default();
+        vars.register(ConsoleVar::new("fov", 90.0).range(60.0, 120.0).archive());
+        vars.register(ConsoleVar::new("name", "player".to_string()).archive());
+        vars.register(ConsoleVar::new("bots", 0));
+        vars
+    }
+
+    #[test]
+    fn test_typed_get_set() {
+        let mut vars = vars();
+        assert_eq!(vars.get::<f64>("fov"), Some(&90.0));
+        assert_eq!(vars.get::<f32>("fov"), None);
+        assert!(vars.set("fov", 100.0).is_ok());
+        assert_eq!(vars.get::<f64>("fov"), Some(&100.0));
+        assert!(vars.set("fov", 100.0f32).is_err());
+        assert!(vars.set("missing", 1.0).is_err());
+    }
+
+    #[test]
+    fn test_set_string() {
+        let mut vars = vars();
+        assert!(vars.set_string("fov", "110").is_ok());
+        assert_eq!(vars.get_string("fov"), Some("110".into()));
+        assert!(vars.set_string("fov", "wide").is_err());
+        assert!(vars.set_string("name", "hello world").is_ok());
+        assert_eq!(vars.get::<String>("name").unwrap(), "hello world");
+    }
+
+    #[test]
+    fn test_range() {
+        let mut vars = vars();
+        assert_eq!(
+            vars.set_string("fov", "200"),
+            Err("'fov' must be between 60 and 120".into())
+        );
+        assert_eq!(vars.get::<f64>("fov"), Some(&90.0));
+        assert_eq!(
+            vars.set_string("fov", "NaN"),
+            Err("'fov' must be between 60 and 120".into())
+        );
+        assert_eq!(vars.get::<f64>("fov"), Some(&90.0));
+    }
+
+    #[test]
+    #[should_panic = "out of its range"]
+    fn test_default_out_of_range() {
+        ConsoleVars::default().register(ConsoleVar::new("fov", 90.0).range(120.0, 60.0));
+    }
+
+    #[test]
+    fn test_reset_and_changes() {
+        let mut vars = vars();
+        vars.set("fov", 90.0).unwrap();
+        vars.set("fov", 100.0).unwrap();
+        vars.reset("fov").unwrap();
+        vars.reset("name").unwrap();
+        assert_eq!(vars.get::<f64>("fov"), Some(&90.0));
+        assert_eq!(vars.changed, vec!["fov", "fov"]);
+    }
+
+    #[test]
+    fn test_toml() {
+        let mut vars = vars();
+        vars.register(ConsoleVar::new("vsync", true).archive());
+        vars.set("fov", 100.5).unwrap();
+        vars.set("bots", 4).unwrap();
+        vars.set("vsync", false).unwrap();
+        let toml = vars.archived_to_toml();
+        assert_eq!(toml, "fov = 100.5\nname = \"player\"\nvsync = false\n");
+        let table = toml.parse::<toml::Table>().unwrap();
+        assert_eq!(table["fov"], toml::Value::Float(100.5));
+        assert_eq!(table["vsync"], toml::Value::Boolean(false));
+
+        let mut loaded = self::vars();
+        loaded.register(ConsoleVar::new("vsync", true).archive());
+        assert!(loaded.load_toml(&toml).is_ok());
+        assert_eq!(loaded.get::<f64>("fov"), Some(&100.5));
+        assert_eq!(loaded.get::<bool>("vsync"), Some(&false));
+        // strings looking like other values are kept as written
+        loaded.set_string("name", "1 # one").unwrap();
+        assert_eq!(
+            loaded.archived_to_toml(),
+            "fov = 100.5\nname = \"1 # one\"\nvsync = false\n"
+        );
+        assert!(loaded.load_toml("fov = 110\nbots = 2").is_ok());
+        assert_eq!(loaded.get::<f64>("fov"), Some(&110.0));
+        assert_eq!(loaded.get::<i32>("bots"), Some(&2));
+        assert_eq!(
+            loaded
+                .load_toml("fov = 200\nmissing = 1")
+                .unwrap_err()
+                .len(),
+            2
+        );
+    }
+}